@@ -0,0 +1,275 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::sync::RwLock;
+
+use crate::constants::{errors, settings};
+
+const MIN_TOKEN_LEN: usize = 4;
+const MIN_SESSION_IDLE_TTL: usize = 60;
+
+/// The runtime-tunable subset of `constants::settings`: TTLs, the length of *new* tokens, and
+/// nothing else. Everything that isn't safe to change without a restart (server address, pool
+/// size, timeouts already baked into issued JWTs, ...) stays a compile-time constant in
+/// `constants::settings` and is untouched by this module.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    pub token_len: usize,
+    pub session_absolute_ttl: usize,
+    pub session_idle_ttl: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            token_len: settings::TOKEN_LEN,
+            session_absolute_ttl: settings::SESSION_ABSOLUTE_TTL,
+            session_idle_ttl: settings::SESSION_IDLE_TTL,
+        }
+    }
+}
+
+impl Config {
+    /// Rejects values that would make a live setting unsafe to use, e.g. a `token_len` short
+    /// enough to threaten the uniqueness loop in `InMemorySessionRepository::save`, or an idle
+    /// TTL longer than the absolute one.
+    pub fn validate(&self) -> Result<(), Box<dyn Error>> {
+        if self.token_len < MIN_TOKEN_LEN {
+            return Err(format!("token_len must be at least {}", MIN_TOKEN_LEN).into());
+        }
+
+        if self.session_idle_ttl < MIN_SESSION_IDLE_TTL {
+            return Err(format!("session_idle_ttl must be at least {}s", MIN_SESSION_IDLE_TTL).into());
+        }
+
+        if self.session_idle_ttl > self.session_absolute_ttl {
+            return Err("session_idle_ttl cannot exceed session_absolute_ttl".into());
+        }
+
+        Ok(())
+    }
+
+    /// Parses a `KEY=VALUE` file, one setting per line (`#` comments and blank lines allowed),
+    /// falling back to `Config::default()` for any key it does not find, then validates the
+    /// result before returning it.
+    pub fn from_file(path: &str) -> Result<Self, Box<dyn Error>> {
+        let contents = fs::read_to_string(path)?;
+        let mut values = HashMap::new();
+
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some((key, value)) = line.split_once('=') {
+                values.insert(key.trim(), value.trim());
+            }
+        }
+
+        let mut config = Config::default();
+        if let Some(value) = values.get("TOKEN_LEN") {
+            config.token_len = value.parse()?;
+        }
+
+        if let Some(value) = values.get("SESSION_ABSOLUTE_TTL") {
+            config.session_absolute_ttl = value.parse()?;
+        }
+
+        if let Some(value) = values.get("SESSION_IDLE_TTL") {
+            config.session_idle_ttl = value.parse()?;
+        }
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Human-readable list of the fields that differ between `self` (about to take effect) and
+    /// `previous` (currently in effect), for logging at reload time.
+    fn diff(&self, previous: &Config) -> Vec<String> {
+        let mut changes = Vec::new();
+
+        if self.token_len != previous.token_len {
+            changes.push(format!("token_len: {} -> {}", previous.token_len, self.token_len));
+        }
+
+        if self.session_absolute_ttl != previous.session_absolute_ttl {
+            changes.push(format!(
+                "session_absolute_ttl: {} -> {}",
+                previous.session_absolute_ttl, self.session_absolute_ttl
+            ));
+        }
+
+        if self.session_idle_ttl != previous.session_idle_ttl {
+            changes.push(format!(
+                "session_idle_ttl: {} -> {}",
+                previous.session_idle_ttl, self.session_idle_ttl
+            ));
+        }
+
+        changes
+    }
+}
+
+/// Holds the process' single live `Config` behind an `RwLock`, so `SessionServiceImplementation`
+/// and `InMemorySessionRepository` always read whatever was last validated and swapped in,
+/// without a restart. Reloading never rewrites sessions already held by
+/// `InMemorySessionRepository`: each one carries its own `expires_at`, computed from whatever
+/// absolute TTL was in effect when it was created, so a new absolute TTL only applies to sessions
+/// created after the reload. The idle TTL is read from the live config on every expiry sweep, so
+/// a new idle TTL applies to existing sessions as well.
+pub struct ConfigStore {
+    current: RwLock<Config>,
+}
+
+impl ConfigStore {
+    pub fn new(initial: Config) -> Self {
+        ConfigStore {
+            current: RwLock::new(initial),
+        }
+    }
+
+    pub fn get(&self) -> Result<Config, Box<dyn Error>> {
+        let guard = self.current.read();
+        if let Err(err) = &guard {
+            error!("read-only lock for live config got poisoned: {}", err);
+            return Err(errors::POISONED.into());
+        }
+
+        Ok(guard.unwrap().clone()) // this line will not panic due to the previous check of Err
+    }
+
+    /// Validates `next`, logs what changed relative to the config currently in effect, and swaps
+    /// it in. Rejects (and keeps the previous config live) if `next` fails validation.
+    pub fn reload(&self, next: Config) -> Result<(), Box<dyn Error>> {
+        next.validate()?;
+
+        let guard = self.current.write();
+        if let Err(err) = &guard {
+            error!("read-write lock for live config got poisoned: {}", err);
+            return Err(errors::POISONED.into());
+        }
+
+        let mut guard = guard.unwrap(); // this line will not panic due to the previous check of Err
+        let changes = next.diff(&guard);
+        if changes.is_empty() {
+            info!("config reload requested but nothing changed");
+        } else {
+            info!("config reloaded: {}", changes.join(", "));
+        }
+
+        *guard = next;
+        Ok(())
+    }
+
+    /// Re-reads `path` and reloads if it parses and validates; logs and leaves the previous
+    /// config in effect otherwise, so a bad edit to the config file never takes a running server
+    /// down. Intended to be called from a SIGHUP handler or a file-watch callback.
+    pub fn reload_from_file(&self, path: &str) {
+        match Config::from_file(path) {
+            Ok(next) => {
+                if let Err(err) = self.reload(next) {
+                    error!("rejected config reload from {}: {}", path, err);
+                }
+            }
+
+            Err(err) => error!("failed to read live config from {}: {}", path, err),
+        }
+    }
+}
+
+/// Spawns a background thread that reloads the config from `path` every time the process
+/// receives SIGHUP, for as long as `store` (expected to be a `'static` singleton) lives.
+pub fn run_sighup_reload(store: &'static ConfigStore, path: String) -> Result<(), Box<dyn Error>> {
+    use signal_hook::consts::SIGHUP;
+    use signal_hook::iterator::Signals;
+
+    let mut signals = Signals::new([SIGHUP])?;
+    std::thread::spawn(move || {
+        for _ in signals.forever() {
+            info!("SIGHUP received, reloading live config from {}", path);
+            store.reload_from_file(&path);
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Config, ConfigStore};
+
+    fn write_temp_config(name: &str, contents: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("oauth_config_test_{}_{}.env", name, std::process::id()));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn default_config_must_validate() {
+        Config::default().validate().unwrap();
+    }
+
+    #[test]
+    fn validate_should_reject_a_too_short_token_len() {
+        let config = Config { token_len: 1, ..Config::default() };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn validate_should_reject_a_too_short_session_idle_ttl() {
+        let config = Config { session_idle_ttl: 1, ..Config::default() };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn validate_should_reject_an_idle_ttl_exceeding_the_absolute_ttl() {
+        let config = Config {
+            session_absolute_ttl: 100,
+            session_idle_ttl: 200,
+            ..Config::default()
+        };
+
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn from_file_should_fall_back_to_defaults_for_missing_keys() {
+        let path = write_temp_config("defaults", "# just a comment\nTOKEN_LEN=64\n");
+
+        let config = Config::from_file(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(config.token_len, 64);
+        assert_eq!(config.session_absolute_ttl, Config::default().session_absolute_ttl);
+        assert_eq!(config.session_idle_ttl, Config::default().session_idle_ttl);
+    }
+
+    #[test]
+    fn from_file_should_reject_an_invalid_value() {
+        let path = write_temp_config("invalid", "TOKEN_LEN=1\n");
+
+        let result = Config::from_file(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn config_store_reload_should_swap_in_a_valid_config() {
+        let store = ConfigStore::new(Config::default());
+        let next = Config { token_len: 64, ..Config::default() };
+
+        store.reload(next.clone()).unwrap();
+        assert_eq!(store.get().unwrap(), next);
+    }
+
+    #[test]
+    fn config_store_reload_should_keep_the_previous_config_on_rejection() {
+        let store = ConfigStore::new(Config::default());
+        let invalid = Config { token_len: 1, ..Config::default() };
+
+        assert!(store.reload(invalid).is_err());
+        assert_eq!(store.get().unwrap(), Config::default());
+    }
+}
@@ -2,7 +2,16 @@ pub mod settings {
     pub const SERVER_IP: &str = "127.0.0.1";
     pub const TOKEN_LEN: usize = 8;
     pub const TOKEN_TIMEOUT: u64 = 86400; // 3600s * 24h
+    pub const REFRESH_TIMEOUT: u64 = 1209600; // 86400s * 14d
+    pub const MAX_LOGIN_ATTEMPTS: u32 = 5;
+    pub const LOGIN_BLOCK_TIMEOUT: u64 = 60; // base backoff unit, doubled per extra offense
+    pub const LOGIN_OFFENSE_TTL: u64 = 86400; // how long a repeat-offender's escalation is remembered
+    pub const EMAIL_OTP_TIMEOUT: u64 = 300; // 5 minutes
+    pub const DEVICE_VERIFICATION_URI: &str = "https://oauth.alvidir.dev/device";
     pub const POOL_SIZE: u32 = 1_u32; // by constants: single thread
+    pub const SESSION_ABSOLUTE_TTL: usize = 86400; // 24h since creation, regardless of activity
+    pub const SESSION_IDLE_TTL: usize = 1800; // 30 minutes without a find/find_by_email hit
+    pub const SESSION_SWEEP_INTERVAL: u64 = 60; // how often the expiry sweeper runs
 }
 
 pub mod environment {
@@ -19,6 +28,7 @@ pub mod environment {
     pub const TEMPLATES: &str = "TEMPLATES";
     pub const PWD_SUFIX: &str = "PWD_SUFIX";
     pub const APP_NAME: &str = "APP_NAME";
+    pub const SESSION_CONFIG_PATH: &str = "SESSION_CONFIG_PATH";
 }
 
 pub mod errors {
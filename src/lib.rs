@@ -5,6 +5,7 @@ extern crate diesel;
 #[macro_use]
 extern crate serde;
 
+pub mod config;
 pub mod metadata;
 pub mod secret;
 pub mod session;
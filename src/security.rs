@@ -0,0 +1,72 @@
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use x25519_dalek::{EphemeralSecret, PublicKey};
+
+use crate::transactions::regex::check_base64;
+
+const IV_LEN: usize = 12;
+
+/// An ephemeral X25519 keypair generated for a single login. Consumed by `derive_key` once the
+/// client's public key is known, so the same exchange can never be reused for a second
+/// Diffie-Hellman, matching the ephemeral-key-per-session intent of the protocol.
+pub struct DirectoryKeyExchange {
+    secret: EphemeralSecret,
+    pub public_key: [u8; 32],
+}
+
+impl DirectoryKeyExchange {
+    pub fn new() -> Self {
+        let secret = EphemeralSecret::random_from_rng(OsRng);
+        let public_key = PublicKey::from(&secret).to_bytes();
+        DirectoryKeyExchange { secret, public_key }
+    }
+
+    /// Derives the 32-byte shared secret against `client_public_key`, to be used as the
+    /// AES-256-GCM key for every directory blob sealed over the lifetime of the session.
+    pub fn derive_key(self, client_public_key: &[u8; 32]) -> [u8; 32] {
+        self.secret
+            .diffie_hellman(&PublicKey::from(*client_public_key))
+            .to_bytes()
+    }
+}
+
+/// Seals `plaintext` with AES-256-GCM under `key`, storing `IV || ciphertext || tag` as a single
+/// Base64-encoded buffer so the server never persists more than ciphertext.
+pub fn encrypt_directory(key: &[u8; 32], plaintext: &[u8]) -> Result<String, &'static str> {
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+
+    let mut iv = [0u8; IV_LEN];
+    OsRng.fill_bytes(&mut iv);
+
+    let sealed = cipher
+        .encrypt(Nonce::from_slice(&iv), plaintext)
+        .map_err(|_| "failed to seal directory payload")?;
+
+    let mut buffer = Vec::with_capacity(IV_LEN + sealed.len());
+    buffer.extend_from_slice(&iv);
+    buffer.extend_from_slice(&sealed);
+
+    Ok(STANDARD.encode(buffer))
+}
+
+/// Reverses `encrypt_directory`: splits the leading 12-byte IV off the decoded buffer and
+/// authenticate-decrypts the remainder, failing closed on any tag mismatch.
+pub fn decrypt_directory(key: &[u8; 32], sealed: &str) -> Result<Vec<u8>, &'static str> {
+    check_base64(sealed).map_err(|_| "malformed directory payload")?;
+
+    let buffer = STANDARD.decode(sealed).map_err(|_| "malformed directory payload")?;
+    if buffer.len() < IV_LEN {
+        return Err("malformed directory payload");
+    }
+
+    let (iv, ciphertext) = buffer.split_at(IV_LEN);
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+
+    cipher
+        .decrypt(Nonce::from_slice(iv), ciphertext)
+        .map_err(|_| "failed to open directory payload")
+}
@@ -1,25 +1,226 @@
+use super::permission::{Permission, PermissionRepository};
+use super::scope::{Scope, ScopeSet};
 use crate::cache::Cache;
+use crate::constants::settings;
 use crate::crypto;
+use crate::mfa::domain::{MfaMethod, Otp};
 use crate::result::{Error, Result};
 use crate::secret::application::SecretRepository;
-use crate::secret::domain::SecretKind;
+use crate::secret::domain::{Secret, SecretKind};
+use crate::security::{decrypt_directory, encrypt_directory, DirectoryKeyExchange};
 use crate::token::application::TokenApplication;
 use crate::token::domain::TokenKind;
-use crate::user::application::UserRepository;
-use crate::user::domain::{Email, Password};
+use crate::user::application::{MailService, UserRepository};
+use crate::user::domain::{Email, Password, PasswordHash, User};
 use std::sync::Arc;
+use std::time::Duration;
+
+/// Tracks a single refresh token in the `Cache`, keyed by its `jti`, so a reused (already
+/// consumed) token can be told apart from a token that was simply never issued.
+#[derive(Serialize, Deserialize)]
+pub(super) struct RefreshRecord {
+    family: String,
+    consumed: bool,
+}
+
+pub(super) fn family_key(family: &str) -> String {
+    format!("refresh_family::{}", family)
+}
+
+/// Generates and stores a `TokenKind::Refresh` token for `sub`, carrying `family`, and records it
+/// in the `Cache` as a fresh (unconsumed) `RefreshRecord` so a later `refresh` call can rotate it.
+/// Shared by `SessionApplication::issue_refresh` and `authorization::AuthorizationApplication`'s
+/// code exchange, since both ultimately hand back a session alongside a refresh token.
+pub(super) async fn issue_refresh_token<'b, C: Cache>(
+    token_app: &TokenApplication<'b, C>,
+    cache: &C,
+    sub: &str,
+    family: &str,
+) -> Result<String> {
+    let refresh = token_app.generate(TokenKind::Refresh, sub)?;
+    token_app.store(&refresh).await?;
+
+    let record = RefreshRecord {
+        family: family.to_string(),
+        consumed: false,
+    };
+
+    cache
+        .save(&refresh.jti, &record, Duration::from_secs(settings::REFRESH_TIMEOUT))
+        .await?;
+
+    token_app.sign(&refresh)
+}
+
+/// Sliding-window failed-attempt counter for `login`, keyed by the identifier being
+/// authenticated against. The entry itself carries its own backoff via the `Cache` TTL: once it
+/// expires the caller is free to try again.
+#[derive(Default, Serialize, Deserialize)]
+struct LoginAttempts {
+    count: u32,
+}
+
+fn login_attempts_key(ident: &str) -> String {
+    format!("login_attempts::{}", ident)
+}
+
+/// How many times `ident` has already been blocked. Tracked in a separate `Cache` entry from
+/// `LoginAttempts`, with its own, much longer TTL, so a repeat offender's backoff keeps
+/// escalating across block cycles instead of resetting to zero the moment one block expires.
+#[derive(Default, Serialize, Deserialize)]
+struct LoginOffenses {
+    count: u32,
+}
+
+fn login_offenses_key(ident: &str) -> String {
+    format!("login_offenses::{}", ident)
+}
 
-pub struct SessionApplication<'a, U: UserRepository, S: SecretRepository, C: Cache> {
+/// Caps how far `register_failed_attempt`'s exponential backoff can grow, so a long-running
+/// offender doesn't push the block TTL into something absurd (or risk overflowing the `2u64.pow`
+/// it's computed from).
+const MAX_BACKOFF_EXPONENT: u32 = 6; // LOGIN_BLOCK_TIMEOUT * 64 at most
+
+/// The X25519-derived AES-256-GCM key for a session's directory payloads, cached under the
+/// session token's `jti` so it lives exactly as long as the session it was negotiated for.
+#[derive(Serialize, Deserialize)]
+struct DirectorySessionKey {
+    key: [u8; 32],
+}
+
+fn directory_key_key(jti: &str) -> String {
+    format!("directory_key::{}", jti)
+}
+
+/// Caches the hash of the OTP most recently emailed to a user by `send_login_email_otp`, keyed
+/// by user id so a second `login` call carrying the code can verify it without round-tripping
+/// through `UserRepository` again.
+fn email_otp_key(user_id: i32) -> String {
+    format!("email_otp::{}", user_id)
+}
+
+/// Mirrors `user::application::webauthn::webauthn_login_key`: the cache entry a prior
+/// `finish_webauthn_assertion` leaves behind, consumed here as the alternative to a TOTP code for
+/// a user whose `preferences.multi_factor` is `MfaMethod::Webauthn`.
+fn webauthn_login_key(user_id: i32) -> String {
+    format!("webauthn_login::{}", user_id)
+}
+
+/// Mirrors `user::application::push_mfa::push_login_key`: the cache entry `resolve_mfa_approval`
+/// leaves behind once the user's device approves a pending push-MFA challenge, consumed here as
+/// the alternative to a TOTP code for a user whose `preferences.multi_factor` is
+/// `MfaMethod::Push`. The challenge itself is requested out of band, via
+/// `UserApplication::request_mfa_approval`, before `login` is retried.
+fn push_login_key(user_id: i32) -> String {
+    format!("push_login::{}", user_id)
+}
+
+/// RFC 7662 introspection response. Inactive tokens must carry no field other than `active`,
+/// so every other field is only ever populated once that check has already passed.
+#[derive(Default, Serialize)]
+pub struct IntrospectionResponse {
+    pub active: bool,
+    pub sub: Option<String>,
+    pub knd: Option<TokenKind>,
+    pub exp: Option<usize>,
+    pub iat: Option<usize>,
+    pub jti: Option<String>,
+    pub scope: Option<String>,
+}
+
+impl IntrospectionResponse {
+    fn inactive() -> Self {
+        IntrospectionResponse::default()
+    }
+}
+
+pub struct SessionApplication<'a, U: UserRepository, S: SecretRepository, C: Cache, P: PermissionRepository, M: MailService> {
     pub user_repo: Arc<U>,
     pub secret_repo: Arc<S>,
     pub token_app: Arc<TokenApplication<'a, C>>,
+    pub cache: Arc<C>,
+    pub permission_repo: Arc<P>,
+    pub mail_srv: Arc<M>,
     pub pwd_sufix: &'a str,
 }
 
-impl<'a, U: UserRepository, S: SecretRepository, C: Cache> SessionApplication<'a, U, S, C> {
+impl<'a, U: UserRepository, S: SecretRepository, C: Cache, P: PermissionRepository, M: MailService> SessionApplication<'a, U, S, C, P, M> {
     /// TODO: create entity Identity and use Credentials here + Totp
-    #[instrument(skip(self))]
-    pub async fn login(&self, ident: &str, pwd: &str, totp: &str) -> Result<String> {
+    ///
+    /// `client_public_key`, when present, is the client's ephemeral X25519 public key: the
+    /// session negotiates a shared AES-256-GCM key against it (see [`Self::seal_directory`] /
+    /// [`Self::open_directory`]) and hands back its own ephemeral public key so the client can
+    /// derive the same secret.
+    #[instrument(skip(self, client_public_key))]
+    pub async fn login(
+        &self,
+        ident: &str,
+        pwd: &str,
+        totp: &str,
+        scope: &str,
+        app_id: &str,
+        client_public_key: Option<&[u8; 32]>,
+    ) -> Result<(String, String, Option<[u8; 32]>)> {
+        let key = login_attempts_key(ident);
+        if let Ok(attempts) = self.cache.find::<LoginAttempts>(&key).await {
+            if attempts.count >= settings::MAX_LOGIN_ATTEMPTS {
+                return Err(Error::TooManyAttempts);
+            }
+        }
+
+        match self.login_unthrottled(ident, pwd, totp, scope, app_id, client_public_key).await {
+            Ok(tokens) => {
+                let _ = self.cache.delete(&key).await;
+                Ok(tokens)
+            }
+
+            Err(err @ (Error::WrongCredentials | Error::Unauthorized)) => {
+                self.register_failed_attempt(ident).await?;
+                Err(err)
+            }
+
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Increments the sliding-window `LoginAttempts` counter for `ident`. Once it reaches
+    /// `MAX_LOGIN_ATTEMPTS` this failure is the one that actually trips a block, so it also bumps
+    /// the long-lived `LoginOffenses` counter and uses *that* (not `LoginAttempts`, which gets
+    /// wiped the moment the block TTL expires) to compute an escalating block duration.
+    async fn register_failed_attempt(&self, ident: &str) -> Result<()> {
+        let key = login_attempts_key(ident);
+        let mut attempts: LoginAttempts = self.cache.find(&key).await.unwrap_or_default();
+        attempts.count += 1;
+
+        if attempts.count < settings::MAX_LOGIN_ATTEMPTS {
+            return self
+                .cache
+                .save(&key, &attempts, Duration::from_secs(settings::LOGIN_BLOCK_TIMEOUT))
+                .await;
+        }
+
+        let offenses_key = login_offenses_key(ident);
+        let mut offenses: LoginOffenses = self.cache.find(&offenses_key).await.unwrap_or_default();
+        offenses.count += 1;
+        self.cache
+            .save(&offenses_key, &offenses, Duration::from_secs(settings::LOGIN_OFFENSE_TTL))
+            .await?;
+
+        let extra = (offenses.count - 1).min(MAX_BACKOFF_EXPONENT);
+        let ttl = settings::LOGIN_BLOCK_TIMEOUT * 2u64.pow(extra);
+
+        self.cache.save(&key, &attempts, Duration::from_secs(ttl)).await
+    }
+
+    async fn login_unthrottled(
+        &self,
+        ident: &str,
+        pwd: &str,
+        totp: &str,
+        scope: &str,
+        app_id: &str,
+        client_public_key: Option<&[u8; 32]>,
+    ) -> Result<(String, String, Option<[u8; 32]>)> {
         let user = {
             if Email::REGEX.is_match(ident) {
                 self.user_repo.find_by_email(&ident.try_into()?).await
@@ -41,24 +242,302 @@ impl<'a, U: UserRepository, S: SecretRepository, C: Cache> SessionApplication<'a
             return Err(Error::WrongCredentials);
         }
 
-        self.secret_repo
-            .find_by_owner_and_kind(user.id, SecretKind::Totp)
-            .await
-            .and_then(|secret| crypto::verify_totp(secret.data(), totp))
-            .map_err(|_| Error::Unauthorized)?;
+        match user.preferences.multi_factor {
+            Some(MfaMethod::Email) => self.verify_email_mfa(&user, totp).await?,
+            Some(MfaMethod::Webauthn) => self.verify_webauthn_mfa(user.id).await?,
+            Some(MfaMethod::Push) => self.verify_push_mfa(user.id).await?,
+            _ => self.verify_totp_or_recovery_code(user.id, totp).await?,
+        }
+
+        // an explicit (or app-default) Permission row, when the deployment has one configured
+        // for `app_id`, takes precedence over the user's flat `granted_scopes`; absent either,
+        // the user simply has no per-app permission model yet and falls back to the flat one.
+        let allowed = Permission::resolve(&*self.permission_repo, user.id, app_id)
+            .unwrap_or_else(|| user.granted_scopes.clone());
 
-        let token = self
+        let granted = ScopeSet::parse(scope).intersect(&allowed);
+
+        let mut token = self
             .token_app
             .generate(TokenKind::Session, &user.id.to_string())?;
 
+        token.scope = granted.to_string();
         self.token_app.store(&token).await?;
-        self.token_app.sign(&token)
+
+        let family = crypto::get_random_string(settings::TOKEN_LEN);
+        let refresh = self.issue_refresh(&user.id.to_string(), &family).await?;
+
+        let server_public_key = match client_public_key {
+            Some(client_public_key) => {
+                let exchange = DirectoryKeyExchange::new();
+                let server_public_key = exchange.public_key;
+                let derived = exchange.derive_key(client_public_key);
+
+                self.cache
+                    .save(
+                        &directory_key_key(&token.jti),
+                        &DirectorySessionKey { key: derived },
+                        Duration::from_secs(settings::TOKEN_TIMEOUT),
+                    )
+                    .await?;
+
+                Some(server_public_key)
+            }
+
+            None => None,
+        };
+
+        Ok((self.token_app.sign(&token)?, refresh, server_public_key))
+    }
+
+    /// Verifies `code` as the alternative to a TOTP code for a user whose `preferences.multi_factor`
+    /// is `MfaMethod::Email`: an empty `code` is the first half of the round trip, so a fresh OTP
+    /// is generated, cached and emailed, and `Error::MfaRequired` is returned to tell the caller
+    /// to retry `login` with the code the user was just sent; a non-empty `code` is the second
+    /// half, verified against that cached OTP and consumed so it cannot be replayed.
+    async fn verify_email_mfa(&self, user: &User, code: &str) -> Result<()> {
+        if code.is_empty() {
+            let otp = Otp::generate();
+            self.cache
+                .save(
+                    &email_otp_key(user.id),
+                    &otp.hash(),
+                    Duration::from_secs(settings::EMAIL_OTP_TIMEOUT),
+                )
+                .await?;
+
+            self.mail_srv.send_email_otp(&user.credentials.email, &otp).await?;
+            return Err(Error::MfaRequired);
+        }
+
+        let key = email_otp_key(user.id);
+        let hash: String = self.cache.find(&key).await.map_err(|_| Error::MfaRequired)?;
+        let otp: Otp = code.try_into().map_err(|_| Error::Unauthorized)?;
+
+        if !crypto::constant_time_eq(hash.as_bytes(), otp.hash().as_bytes()) {
+            return Err(Error::Unauthorized);
+        }
+
+        self.cache.delete(&key).await
+    }
+
+    /// Verifies the WebAuthn alternative to a TOTP code for a user whose `preferences.multi_factor`
+    /// is `MfaMethod::Webauthn`: the actual assertion ceremony (`begin_webauthn_assertion` /
+    /// `finish_webauthn_assertion`) runs out of band against `UserApplication`, before `login` is
+    /// retried with an empty `totp`; a completed assertion leaves a short-lived proof behind under
+    /// `webauthn_login_key`, which is what this consumes. Absent that proof, the ceremony has not
+    /// (yet) completed, so the caller is told to go run it.
+    async fn verify_webauthn_mfa(&self, user_id: i32) -> Result<()> {
+        let key = webauthn_login_key(user_id);
+        self.cache.find::<bool>(&key).await.map_err(|_| Error::MfaRequired)?;
+        self.cache.delete(&key).await
+    }
+
+    /// Verifies the push-MFA alternative to a TOTP code for a user whose `preferences.multi_factor`
+    /// is `MfaMethod::Push`: the approval round trip itself (`request_mfa_approval`, the user's
+    /// device calling `resolve_mfa_approval`) runs out of band against `UserApplication`, before
+    /// `login` is retried with an empty `totp`; an approved challenge leaves a short-lived proof
+    /// behind under `push_login_key`, which is what this consumes. Absent that proof — whether the
+    /// challenge is still pending, was denied, or was never requested — the caller is told to go
+    /// (re)run it.
+    async fn verify_push_mfa(&self, user_id: i32) -> Result<()> {
+        let key = push_login_key(user_id);
+        self.cache.find::<bool>(&key).await.map_err(|_| Error::MfaRequired)?;
+        self.cache.delete(&key).await
+    }
+
+    /// Verifies `code` for a user whose `preferences.multi_factor` falls through to TOTP: tried
+    /// first as a live code against the user's `SecretKind::Totp` secret, then, if that fails, as
+    /// a one-time recovery code against `SecretKind::Recovery`, the same way
+    /// `UserApplication::consume_recovery_code` does, so a user who has lost their authenticator
+    /// app isn't locked out of login entirely. A user with no TOTP secret enrolled has no second
+    /// factor to check, so `code` is not required.
+    async fn verify_totp_or_recovery_code(&self, user_id: i32, code: &str) -> Result<()> {
+        match self.secret_repo.find_by_owner_and_kind(user_id, SecretKind::Totp).await {
+            Ok(secret) if crypto::verify_totp(secret.data(), code).is_ok() => return Ok(()),
+            Err(Error::NotFound) => return Ok(()),
+            _ => {}
+        }
+
+        if self.consume_recovery_code(user_id, code).await? {
+            return Ok(());
+        }
+
+        Err(Error::Unauthorized)
+    }
+
+    /// Consumes a one-time recovery code for `user_id`. Returns whether `code` matched a
+    /// still-unused recovery code; a matched code is removed so it cannot be replayed.
+    async fn consume_recovery_code(&self, user_id: i32, code: &str) -> Result<bool> {
+        let Ok(candidate) = Password::try_from(code.to_string()) else {
+            return Ok(false);
+        };
+
+        let Ok(secret) = self.secret_repo.find_by_owner_and_kind(user_id, SecretKind::Recovery).await else {
+            return Ok(false);
+        };
+
+        let data = String::from_utf8_lossy(secret.data()).into_owned();
+        let mut remaining: Vec<&str> = data.lines().collect();
+
+        let Some(pos) = remaining
+            .iter()
+            .position(|hash| PasswordHash::try_from(hash.to_string()).is_ok_and(|hash| hash.verify(&candidate)))
+        else {
+            return Ok(false);
+        };
+
+        remaining.remove(pos);
+        let updated = Secret::new(user_id, SecretKind::Recovery, remaining.join("\n").into_bytes());
+        self.secret_repo.save(&updated).await?;
+
+        Ok(true)
     }
 
     #[instrument(skip(self))]
     pub async fn logout(&self, token: &str) -> Result<()> {
         logout_strategy::<C>(&self.token_app, token).await
     }
+
+    /// Exchanges a valid, non-revoked refresh token for a new session/refresh pair, rotating
+    /// the refresh token in the process: the presented token is marked consumed and a brand new
+    /// one, carrying the same family id, takes its place. A refresh token whose `jti` is already
+    /// marked consumed is a theft signal — the whole family is revoked rather than honored.
+    #[instrument(skip(self))]
+    pub async fn refresh(&self, token: &str) -> Result<(String, String)> {
+        let token = self.token_app.decode(token)?;
+        if !token.knd.is_refresh() {
+            return Err(Error::InvalidToken);
+        }
+
+        let record: RefreshRecord = self
+            .cache
+            .find(&token.jti)
+            .await
+            .map_err(|_| Error::InvalidToken)?;
+
+        if self.cache.find::<bool>(&family_key(&record.family)).await.is_ok() {
+            return Err(Error::Unauthorized);
+        }
+
+        if record.consumed {
+            // reuse of an already-rotated refresh token: treat it as theft and burn the family.
+            self.cache
+                .save(&family_key(&record.family), &true, Duration::from_secs(settings::REFRESH_TIMEOUT))
+                .await?;
+
+            return Err(Error::Unauthorized);
+        }
+
+        self.cache
+            .save(
+                &token.jti,
+                &RefreshRecord {
+                    family: record.family.clone(),
+                    consumed: true,
+                },
+                Duration::from_secs(settings::REFRESH_TIMEOUT),
+            )
+            .await?;
+
+        let session = self
+            .token_app
+            .generate(TokenKind::Session, &token.sub)?;
+
+        self.token_app.store(&session).await?;
+        let refresh = self.issue_refresh(&token.sub, &record.family).await?;
+
+        Ok((self.token_app.sign(&session)?, refresh))
+    }
+
+    async fn issue_refresh(&self, sub: &str, family: &str) -> Result<String> {
+        issue_refresh_token(&*self.token_app, &*self.cache, sub, family).await
+    }
+
+    /// Per RFC 7662: decodes and verifies `token`, and reports whether it is currently usable.
+    /// An expired, revoked, malformed, or unsigned token yields `{ active: false }` with no
+    /// other field populated, so resource servers cannot learn anything about a bad token beyond
+    /// the fact that it is not usable.
+    #[instrument(skip(self))]
+    pub async fn introspect(&self, token: &str) -> IntrospectionResponse {
+        let Ok(token) = self.token_app.decode(token) else {
+            return IntrospectionResponse::inactive();
+        };
+
+        if self.token_app.is_revoked(&token.jti).await.unwrap_or(true) {
+            return IntrospectionResponse::inactive();
+        }
+
+        IntrospectionResponse {
+            active: true,
+            sub: Some(token.sub),
+            knd: Some(token.knd),
+            exp: Some(token.exp),
+            iat: Some(token.iat),
+            jti: Some(token.jti),
+            scope: Some(token.scope),
+        }
+    }
+
+    /// Gates downstream operations on the scopes actually granted to `token`: decodes and
+    /// verifies it, then checks that every scope in `required` was granted at login time.
+    ///
+    /// This lives on `SessionApplication` rather than `TokenApplication` because `ScopeSet` and
+    /// the notion of a scope "covering" a requirement are session/login concerns — the same
+    /// vocabulary `login_unthrottled` and `introspect` already use in this file — not something
+    /// `TokenApplication` itself (which only knows how to decode, sign, and store raw tokens)
+    /// has any other reason to depend on.
+    #[instrument(skip(self))]
+    pub async fn authorize(&self, token: &str, required: &[Scope]) -> Result<()> {
+        let token = self.token_app.decode(token)?;
+        let granted = ScopeSet::parse(&token.scope);
+
+        if !granted.covers(required) {
+            return Err(Error::InsufficientScope);
+        }
+
+        Ok(())
+    }
+
+    /// Single-scope convenience over `authorize`, for callers (e.g. the gRPC layer) that want to
+    /// reject a request lacking one particular scope, such as with `Status::permission_denied`,
+    /// without building a one-element slice themselves.
+    #[instrument(skip(self))]
+    pub async fn has_scope(&self, token: &str, scope: &Scope) -> Result<bool> {
+        match self.authorize(token, &[scope.clone()]).await {
+            Ok(()) => Ok(true),
+            Err(Error::InsufficientScope) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Seals `plaintext` under the AES-256-GCM key negotiated for `token` at login time. Fails
+    /// with `Error::InvalidToken` if `token` is invalid or no key exchange was performed for it
+    /// (i.e. login was not given a `client_public_key`).
+    #[instrument(skip(self, plaintext))]
+    pub async fn seal_directory(&self, token: &str, plaintext: &[u8]) -> Result<String> {
+        let token = self.token_app.decode(token)?;
+        let session_key: DirectorySessionKey = self
+            .cache
+            .find(&directory_key_key(&token.jti))
+            .await
+            .map_err(|_| Error::InvalidToken)?;
+
+        encrypt_directory(&session_key.key, plaintext).map_err(|_| Error::InvalidToken)
+    }
+
+    /// Reverses [`Self::seal_directory`]: opens `sealed` under the key negotiated for `token`.
+    #[instrument(skip(self, sealed))]
+    pub async fn open_directory(&self, token: &str, sealed: &str) -> Result<Vec<u8>> {
+        let token = self.token_app.decode(token)?;
+        let session_key: DirectorySessionKey = self
+            .cache
+            .find(&directory_key_key(&token.jti))
+            .await
+            .map_err(|_| Error::InvalidToken)?;
+
+        decrypt_directory(&session_key.key, sealed).map_err(|_| Error::InvalidToken)
+    }
 }
 
 pub(super) async fn logout_strategy<'b, C: Cache>(
@@ -75,23 +554,34 @@ pub(super) async fn logout_strategy<'b, C: Cache>(
 
 #[cfg(test)]
 pub mod tests {
-    use super::SessionApplication;
+    use super::super::permission::tests::PermissionRepositoryMock;
+    use super::{login_attempts_key, login_offenses_key, LoginAttempts, LoginOffenses, SessionApplication};
     use crate::cache::tests::InMemoryCache;
     use crate::secret::application::tests::SecretRepositoryMock;
     use crate::secret::domain::{Secret, SecretKind};
+    use crate::security::{decrypt_directory, DirectoryKeyExchange};
     use crate::token::application::tests::{
         new_token, new_token_application, PRIVATE_KEY, PUBLIC_KEY,
     };
     use crate::token::domain::{Token, TokenKind};
-    use crate::user::{application::tests::UserRepositoryMock, domain::User};
+    use crate::user::{
+        application::tests::{MailServiceMock, UserRepositoryMock},
+        domain::User,
+    };
     use crate::{
         crypto,
         result::{Error, Result},
     };
     use std::sync::Arc;
 
-    pub fn new_session_application<'a>(
-    ) -> SessionApplication<'a, UserRepositoryMock, SecretRepositoryMock, InMemoryCache> {
+    pub fn new_session_application<'a>() -> SessionApplication<
+        'a,
+        UserRepositoryMock,
+        SecretRepositoryMock,
+        InMemoryCache,
+        PermissionRepositoryMock,
+        MailServiceMock,
+    > {
         let user_repo = UserRepositoryMock::default();
         let secret_repo = SecretRepositoryMock::default();
         let token_app = new_token_application();
@@ -100,6 +590,9 @@ pub mod tests {
             user_repo: Arc::new(user_repo),
             secret_repo: Arc::new(secret_repo),
             token_app: Arc::new(token_app),
+            cache: Arc::new(InMemoryCache::default()),
+            permission_repo: Arc::new(PermissionRepositoryMock::default()),
+            mail_srv: Arc::new(MailServiceMock::default()),
             pwd_sufix: "::test",
         }
     }
@@ -118,8 +611,8 @@ pub mod tests {
         let mut app = new_session_application();
         app.secret_repo = Arc::new(secret_repo);
 
-        let token = app
-            .login("username@server.domain", "abcABC123&", "")
+        let (token, _refresh, _) = app
+            .login("username@server.domain", "abcABC123&", "", "", "", None)
             .await
             .map_err(|err| {
                 println!(
@@ -146,8 +639,8 @@ pub mod tests {
 
         let mut app = new_session_application();
         app.secret_repo = Arc::new(secret_repo);
-        let token = app
-            .login("username", "abcABC123&", "")
+        let (token, _refresh, _) = app
+            .login("username", "abcABC123&", "", "", "", None)
             .await
             .map_err(|err| {
                 println!(
@@ -164,8 +657,8 @@ pub mod tests {
     async fn login_with_totp_should_not_fail() {
         let app = new_session_application();
         let code = crypto::generate_totp(b"secret_data").unwrap().generate();
-        let token = app
-            .login("username", "abcABC123&", &code)
+        let (token, _refresh, _) = app
+            .login("username", "abcABC123&", &code, "", "", None)
             .await
             .map_err(|err| {
                 println!(
@@ -192,7 +685,7 @@ pub mod tests {
 
         let code = crypto::generate_totp(b"secret_data").unwrap().generate();
 
-        app.login("username@server.domain", "abcABC123&", &code)
+        app.login("username@server.domain", "abcABC123&", &code, "", "", None)
             .await
             .map_err(|err| assert_eq!(err.to_string(), Error::WrongCredentials.to_string()))
             .unwrap_err();
@@ -202,7 +695,7 @@ pub mod tests {
     async fn login_wrong_password_should_fail() {
         let app = new_session_application();
         let code = crypto::generate_totp(b"secret_data").unwrap().generate();
-        app.login("username", "fake_password", &code)
+        app.login("username", "fake_password", &code, "", "", None)
             .await
             .map_err(|err| assert_eq!(err.to_string(), Error::WrongCredentials.to_string()))
             .unwrap_err();
@@ -212,7 +705,7 @@ pub mod tests {
     async fn login_wrong_totp_should_fail() {
         let app = new_session_application();
 
-        app.login("username", "abcABC123&", "fake_totp")
+        app.login("username", "abcABC123&", "fake_totp", "", "", None)
             .await
             .map_err(|err| assert_eq!(err.to_string(), Error::Unauthorized.to_string()))
             .unwrap_err();
@@ -252,4 +745,237 @@ pub mod tests {
             .map_err(|err| assert_eq!(err.to_string(), Error::InvalidToken.to_string()))
             .unwrap_err();
     }
+
+    #[tokio::test]
+    async fn refresh_should_not_fail() {
+        let app = new_session_application();
+        let code = crypto::generate_totp(b"secret_data").unwrap().generate();
+        let (_, refresh, _) = app.login("username", "abcABC123&", &code, "", "", None).await.unwrap();
+
+        let (_, new_refresh) = app
+            .refresh(&refresh)
+            .await
+            .map_err(|err| println!("-\trefresh_should_not_fail has failed with error {}", err))
+            .unwrap();
+
+        assert_ne!(refresh, new_refresh);
+    }
+
+    #[tokio::test]
+    async fn refresh_reused_token_should_fail() {
+        let app = new_session_application();
+        let code = crypto::generate_totp(b"secret_data").unwrap().generate();
+        let (_, refresh, _) = app.login("username", "abcABC123&", &code, "", "", None).await.unwrap();
+
+        app.refresh(&refresh).await.unwrap();
+
+        // the refresh token was already rotated away, presenting it again is a reuse/theft
+        // signal and must be rejected even though it has not expired yet.
+        app.refresh(&refresh)
+            .await
+            .map_err(|err| assert_eq!(err.to_string(), Error::Unauthorized.to_string()))
+            .unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn refresh_session_token_kind_should_fail() {
+        let token = crypto::sign_jwt(&PRIVATE_KEY, new_token(TokenKind::Session)).unwrap();
+        let app = new_session_application();
+
+        app.refresh(&token)
+            .await
+            .map_err(|err| assert_eq!(err.to_string(), Error::InvalidToken.to_string()))
+            .unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn introspect_active_token_should_report_active() {
+        let app = new_session_application();
+        let code = crypto::generate_totp(b"secret_data").unwrap().generate();
+        let (token, _refresh, _) = app.login("username", "abcABC123&", &code, "", "", None).await.unwrap();
+
+        let introspection = app.introspect(&token).await;
+        assert!(introspection.active);
+        assert_eq!(introspection.sub, Some("123".to_string()));
+    }
+
+    #[tokio::test]
+    async fn introspect_revoked_token_should_report_inactive() {
+        let token = crypto::sign_jwt(&PRIVATE_KEY, new_token(TokenKind::Session)).unwrap();
+        let app = new_session_application();
+        app.logout(&token).await.unwrap();
+
+        let introspection = app.introspect(&token).await;
+        assert!(!introspection.active);
+        assert_eq!(introspection.sub, None);
+    }
+
+    #[tokio::test]
+    async fn login_should_block_after_max_attempts() {
+        let app = new_session_application();
+
+        for _ in 0..crate::constants::settings::MAX_LOGIN_ATTEMPTS {
+            app.login("username", "fake_password", "", "", "", None)
+                .await
+                .map_err(|err| assert_eq!(err.to_string(), Error::WrongCredentials.to_string()))
+                .unwrap_err();
+        }
+
+        app.login("username", "abcABC123&", "", "", "", None)
+            .await
+            .map_err(|err| assert_eq!(err.to_string(), Error::TooManyAttempts.to_string()))
+            .unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn login_should_reset_attempts_on_success() {
+        let app = new_session_application();
+
+        app.login("username", "fake_password", "", "", "", None).await.unwrap_err();
+        let code = crypto::generate_totp(b"secret_data").unwrap().generate();
+        app.login("username", "abcABC123&", &code, "", "", None)
+            .await
+            .map_err(|err| println!("-\tlogin_should_reset_attempts_on_success has failed with error {}", err))
+            .unwrap();
+
+        // the counter must have been cleared by the successful login above.
+        assert!(app.cache.find::<LoginAttempts>(&login_attempts_key("username")).await.is_err());
+        app.login("username", "fake_password", "", "", "", None).await.unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn login_should_record_offense_independently_of_attempt_counter() {
+        let app = new_session_application();
+
+        for _ in 0..crate::constants::settings::MAX_LOGIN_ATTEMPTS {
+            app.login("username", "fake_password", "", "", "", None).await.unwrap_err();
+        }
+
+        // the attempt counter trips the block on the MAX_LOGIN_ATTEMPTS-th failure, which must
+        // also have recorded a long-lived offense, independent of the (short-lived) attempt
+        // counter itself, so a second block cycle for this ident escalates instead of starting
+        // fresh at count == 0.
+        let offenses: LoginOffenses = app.cache.find(&login_offenses_key("username")).await.unwrap();
+        assert_eq!(offenses.count, 1);
+    }
+
+    #[tokio::test]
+    async fn authorize_missing_scope_should_fail() {
+        use super::super::scope::Scope;
+
+        let app = new_session_application();
+        let code = crypto::generate_totp(b"secret_data").unwrap().generate();
+        let (token, _refresh, _) = app.login("username", "abcABC123&", &code, "", "", None).await.unwrap();
+
+        app.authorize(&token, &[Scope::new("directory:read")])
+            .await
+            .map_err(|err| assert_eq!(err.to_string(), Error::InsufficientScope.to_string()))
+            .unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn authorize_with_no_required_scope_should_not_fail() {
+        let app = new_session_application();
+        let code = crypto::generate_totp(b"secret_data").unwrap().generate();
+        let (token, _refresh, _) = app.login("username", "abcABC123&", &code, "", "", None).await.unwrap();
+
+        app.authorize(&token, &[])
+            .await
+            .map_err(|err| println!("-\tauthorize_with_no_required_scope_should_not_fail has failed with error {}", err))
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn login_grants_scope_from_configured_permission() {
+        let permission_repo = PermissionRepositoryMock {
+            fn_find_by_user_and_app: Some(|_, target_user_id, target_app_id| {
+                assert_eq!(target_user_id, 123, "unexpected user id");
+                assert_eq!(target_app_id, "app-1", "unexpected app id");
+
+                Ok(super::super::permission::Permission::new(
+                    target_user_id,
+                    target_app_id,
+                    super::super::scope::ScopeSet::parse("directory:read"),
+                ))
+            }),
+            ..Default::default()
+        };
+
+        let mut app = new_session_application();
+        app.permission_repo = Arc::new(permission_repo);
+
+        let code = crypto::generate_totp(b"secret_data").unwrap().generate();
+        let (token, _refresh, _) = app
+            .login("username", "abcABC123&", &code, "directory:read directory:write", "app-1", None)
+            .await
+            .unwrap();
+
+        let session: Token = crypto::decode_jwt(&PUBLIC_KEY, &token).unwrap();
+        assert_eq!(
+            session.scope, "directory:read",
+            "expected only the scope granted by the configured Permission"
+        );
+    }
+
+    #[tokio::test]
+    async fn login_without_a_configured_permission_falls_back_to_flat_scopes() {
+        // no Permission row exists for this (user, app) pair (the mock reports NotFound by
+        // default), so login must fall back to the user's flat granted_scopes rather than
+        // treating the absence as "grant nothing".
+        let app = new_session_application();
+        let code = crypto::generate_totp(b"secret_data").unwrap().generate();
+
+        app.login("username", "abcABC123&", &code, "directory:read", "app-without-permissions", None)
+            .await
+            .map_err(|err| {
+                println!(
+                    "-\tlogin_without_a_configured_permission_falls_back_to_flat_scopes has failed with error {}",
+                    err
+                )
+            })
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn introspect_malformed_token_should_report_inactive() {
+        let app = new_session_application();
+
+        let introspection = app.introspect("not-a-real-token").await;
+        assert!(!introspection.active);
+    }
+
+    #[tokio::test]
+    async fn login_with_client_public_key_should_negotiate_directory_key() {
+        let app = new_session_application();
+        let client_exchange = DirectoryKeyExchange::new();
+        let client_public_key = client_exchange.public_key;
+
+        let code = crypto::generate_totp(b"secret_data").unwrap().generate();
+        let (token, _refresh, server_public_key) = app
+            .login("username", "abcABC123&", &code, "", "", Some(&client_public_key))
+            .await
+            .unwrap();
+
+        let server_public_key = server_public_key.expect("server should negotiate a key when asked to");
+        let client_key = client_exchange.derive_key(&server_public_key);
+
+        let sealed = app.seal_directory(&token, b"top secret").await.unwrap();
+        let opened = app.open_directory(&token, &sealed).await.unwrap();
+        assert_eq!(opened, b"top secret");
+
+        // the client must be able to derive the very same key independently from the server's
+        // public key handed back at login, without ever learning the server's private scalar.
+        assert_eq!(decrypt_directory(&client_key, &sealed).unwrap(), b"top secret");
+    }
+
+    #[tokio::test]
+    async fn login_without_client_public_key_should_not_negotiate_directory_key() {
+        let app = new_session_application();
+        let code = crypto::generate_totp(b"secret_data").unwrap().generate();
+        let (token, _refresh, server_public_key) =
+            app.login("username", "abcABC123&", &code, "", "", None).await.unwrap();
+
+        assert!(server_public_key.is_none());
+        app.seal_directory(&token, b"irrelevant").await.unwrap_err();
+    }
 }
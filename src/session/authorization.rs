@@ -0,0 +1,360 @@
+use super::application::issue_refresh_token;
+use crate::cache::Cache;
+use crate::constants::settings;
+use crate::crypto;
+use crate::result::{Error, Result};
+use crate::token::application::TokenApplication;
+use crate::token::domain::TokenKind;
+use crate::transactions::regex::{resolve_and_check_url, DnsResolver, UrlPolicy};
+use std::sync::Arc;
+use std::time::Duration;
+
+const AUTHORIZATION_CODE_LEN: usize = 32;
+const AUTHORIZATION_CODE_TIMEOUT: u64 = 60; // 60s
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CodeChallengeMethod {
+    Plain,
+    S256,
+}
+
+impl TryFrom<&str> for CodeChallengeMethod {
+    type Error = Error;
+
+    fn try_from(value: &str) -> Result<Self> {
+        match value {
+            "plain" => Ok(CodeChallengeMethod::Plain),
+            "S256" => Ok(CodeChallengeMethod::S256),
+            _ => Err(Error::InvalidToken),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct AuthorizationCode {
+    client_id: String,
+    redirect_uri: String,
+    scope: String,
+    state: String,
+    user_id: String,
+    code_challenge: String,
+    code_challenge_method: CodeChallengeMethod,
+}
+
+pub struct AuthorizationApplication<'a, C: Cache, R: DnsResolver> {
+    pub cache: Arc<C>,
+    pub token_app: Arc<TokenApplication<'a, C>>,
+    pub dns_resolver: Arc<R>,
+}
+
+impl<'a, C: Cache, R: DnsResolver> AuthorizationApplication<'a, C, R> {
+    /// Mints a short-lived, one-time authorization code for the user behind `session`, an
+    /// already authenticated `TokenKind::Session` token that this decodes and verifies itself,
+    /// the same way every other gating method in this module family does, rather than trusting a
+    /// caller-supplied user id. The code binds the PKCE challenge and the requesting client so
+    /// `token` can later verify both the code verifier and the redirect_uri. `redirect_uri` is
+    /// validated against [`resolve_and_check_url`] before anything is cached, since it's the one
+    /// attacker-supplied URL in this flow and is later handed back to a user-agent as a redirect
+    /// target: a hostname is resolved through `dns_resolver` rather than trusted at face value,
+    /// so a redirect_uri that only resolves to a non-routable address via DNS is rejected the
+    /// same as a literal one. Returns `(code, state)`, with `state` echoed back unmodified so the
+    /// caller's redirect endpoint can round-trip it to the client for CSRF verification.
+    #[instrument(skip(self, session, code_challenge))]
+    pub async fn authorize(
+        &self,
+        session: &str,
+        client_id: &str,
+        redirect_uri: &str,
+        scope: &str,
+        state: &str,
+        code_challenge: &str,
+        code_challenge_method: &str,
+    ) -> Result<(String, String)> {
+        let session = self.token_app.decode(session)?;
+        if !session.knd.is_session() {
+            return Err(Error::InvalidToken);
+        }
+
+        resolve_and_check_url(redirect_uri, &UrlPolicy::default(), &*self.dns_resolver).map_err(|_| Error::InvalidToken)?;
+
+        let code_challenge_method = CodeChallengeMethod::try_from(code_challenge_method)?;
+        let code = crypto::get_random_string(AUTHORIZATION_CODE_LEN);
+
+        let entry = AuthorizationCode {
+            client_id: client_id.to_string(),
+            redirect_uri: redirect_uri.to_string(),
+            scope: scope.to_string(),
+            state: state.to_string(),
+            user_id: session.sub,
+            code_challenge: code_challenge.to_string(),
+            code_challenge_method,
+        };
+
+        self.cache
+            .save(&code, &entry, Duration::from_secs(AUTHORIZATION_CODE_TIMEOUT))
+            .await?;
+
+        Ok((code, state.to_string()))
+    }
+
+    /// Exchanges a previously issued authorization `code` for a fresh session/refresh pair,
+    /// verifying the PKCE `code_verifier` against the challenge stored at `authorize` time. Codes
+    /// are single-use: they are deleted from the cache as soon as they are read, regardless of
+    /// the outcome of the verification that follows, so a replayed code always fails.
+    #[instrument(skip(self, code_verifier))]
+    pub async fn token(&self, code: &str, code_verifier: &str, redirect_uri: &str) -> Result<(String, String)> {
+        let entry: AuthorizationCode = self.cache.find(code).await.map_err(|_| Error::InvalidToken)?;
+        self.cache.delete(code).await?;
+
+        if entry.redirect_uri != redirect_uri {
+            return Err(Error::InvalidToken);
+        }
+
+        if !Self::verify_challenge(entry.code_challenge_method, code_verifier, &entry.code_challenge) {
+            return Err(Error::InvalidToken);
+        }
+
+        let mut token = self.token_app.generate(TokenKind::Session, &entry.user_id)?;
+        token.scope = entry.scope;
+        self.token_app.store(&token).await?;
+
+        let family = crypto::get_random_string(settings::TOKEN_LEN);
+        let refresh = issue_refresh_token(&*self.token_app, &*self.cache, &entry.user_id, &family).await?;
+
+        Ok((self.token_app.sign(&token)?, refresh))
+    }
+
+    fn verify_challenge(method: CodeChallengeMethod, verifier: &str, challenge: &str) -> bool {
+        match method {
+            CodeChallengeMethod::Plain => crypto::constant_time_eq(verifier.as_bytes(), challenge.as_bytes()),
+            CodeChallengeMethod::S256 => {
+                let computed = crypto::sha256_base64url(verifier.as_bytes());
+                crypto::constant_time_eq(computed.as_bytes(), challenge.as_bytes())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::tests::InMemoryCache;
+    use crate::token::application::tests::new_token_application;
+    use std::net::IpAddr;
+
+    struct StaticResolver(Vec<IpAddr>);
+
+    impl DnsResolver for StaticResolver {
+        fn resolve(&self, _host: &str) -> std::result::Result<Vec<IpAddr>, &'static str> {
+            Ok(self.0.clone())
+        }
+    }
+
+    /// Resolves every hostname to a single public address, so tests exercise `authorize`'s
+    /// redirect_uri validation without depending on (or making) a real DNS lookup.
+    fn new_authorization_application<'a>() -> AuthorizationApplication<'a, InMemoryCache, StaticResolver> {
+        new_authorization_application_with_resolver(StaticResolver(vec!["93.184.216.34".parse().unwrap()]))
+    }
+
+    fn new_authorization_application_with_resolver<'a>(
+        resolver: StaticResolver,
+    ) -> AuthorizationApplication<'a, InMemoryCache, StaticResolver> {
+        AuthorizationApplication {
+            cache: Arc::new(InMemoryCache::default()),
+            token_app: Arc::new(new_token_application()),
+            dns_resolver: Arc::new(resolver),
+        }
+    }
+
+    /// Mints a signed `TokenKind::Session` token for `sub`, the same way `authorize` itself
+    /// expects to be handed one, so tests can exercise it without a real login.
+    fn session_token<R: DnsResolver>(app: &AuthorizationApplication<'_, InMemoryCache, R>, sub: &str) -> String {
+        let token = app.token_app.generate(TokenKind::Session, sub).unwrap();
+        app.token_app.sign(&token).unwrap()
+    }
+
+    #[test]
+    fn verify_challenge_plain_should_match_equal_verifier() {
+        assert!(AuthorizationApplication::<InMemoryCache, StaticResolver>::verify_challenge(
+            CodeChallengeMethod::Plain,
+            "verifier",
+            "verifier",
+        ));
+    }
+
+    #[test]
+    fn verify_challenge_plain_should_not_match_different_verifier() {
+        assert!(!AuthorizationApplication::<InMemoryCache, StaticResolver>::verify_challenge(
+            CodeChallengeMethod::Plain,
+            "verifier",
+            "other",
+        ));
+    }
+
+    #[test]
+    fn verify_challenge_s256_should_match_computed_challenge() {
+        let verifier = "abcABC123&verifier";
+        let challenge = crypto::sha256_base64url(verifier.as_bytes());
+
+        assert!(AuthorizationApplication::<InMemoryCache, StaticResolver>::verify_challenge(
+            CodeChallengeMethod::S256,
+            verifier,
+            &challenge,
+        ));
+    }
+
+    #[test]
+    fn verify_challenge_s256_should_not_match_wrong_challenge() {
+        assert!(!AuthorizationApplication::<InMemoryCache, StaticResolver>::verify_challenge(
+            CodeChallengeMethod::S256,
+            "verifier",
+            "wrong_challenge",
+        ));
+    }
+
+    #[tokio::test]
+    async fn authorize_and_token_round_trip_should_not_fail() {
+        let app = new_authorization_application();
+        let session = session_token(&app, "123");
+        let (code, state) = app
+            .authorize(
+                &session,
+                "client",
+                "https://example.com/callback",
+                "read",
+                "xyz",
+                "verifier",
+                "plain",
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(state, "xyz");
+
+        let (token, refresh) = app
+            .token(&code, "verifier", "https://example.com/callback")
+            .await
+            .unwrap();
+
+        assert!(!token.is_empty());
+        assert!(!refresh.is_empty());
+    }
+
+    #[tokio::test]
+    async fn authorize_with_invalid_session_should_fail() {
+        let app = new_authorization_application();
+        app.authorize(
+            "not-a-real-token",
+            "client",
+            "https://example.com/callback",
+            "read",
+            "xyz",
+            "verifier",
+            "plain",
+        )
+        .await
+        .unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn authorize_with_refresh_token_should_fail() {
+        let app = new_authorization_application();
+        let token = app.token_app.generate(TokenKind::Refresh, "123").unwrap();
+        let refresh = app.token_app.sign(&token).unwrap();
+
+        app.authorize(
+            &refresh,
+            "client",
+            "https://example.com/callback",
+            "read",
+            "xyz",
+            "verifier",
+            "plain",
+        )
+        .await
+        .unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn authorize_should_reject_non_global_redirect_uri() {
+        let app = new_authorization_application();
+        let session = session_token(&app, "123");
+        app.authorize(
+            &session,
+            "client",
+            "http://127.0.0.1/callback",
+            "read",
+            "xyz",
+            "verifier",
+            "plain",
+        )
+        .await
+        .unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn authorize_should_reject_redirect_uri_resolving_to_non_global_address() {
+        let app = new_authorization_application_with_resolver(StaticResolver(vec!["127.0.0.1".parse().unwrap()]));
+        let session = session_token(&app, "123");
+
+        app.authorize(
+            &session,
+            "client",
+            "https://rebinder.example.com/callback",
+            "read",
+            "xyz",
+            "verifier",
+            "plain",
+        )
+        .await
+        .unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn token_with_wrong_verifier_should_fail() {
+        let app = new_authorization_application();
+        let session = session_token(&app, "123");
+        let (code, _) = app
+            .authorize(
+                &session,
+                "client",
+                "https://example.com/callback",
+                "read",
+                "xyz",
+                "verifier",
+                "plain",
+            )
+            .await
+            .unwrap();
+
+        app.token(&code, "wrong", "https://example.com/callback")
+            .await
+            .unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn token_reused_code_should_fail() {
+        let app = new_authorization_application();
+        let session = session_token(&app, "123");
+        let (code, _) = app
+            .authorize(
+                &session,
+                "client",
+                "https://example.com/callback",
+                "read",
+                "xyz",
+                "verifier",
+                "plain",
+            )
+            .await
+            .unwrap();
+
+        app.token(&code, "verifier", "https://example.com/callback")
+            .await
+            .unwrap();
+
+        app.token(&code, "verifier", "https://example.com/callback")
+            .await
+            .unwrap_err();
+    }
+}
@@ -1,12 +1,15 @@
 use std::error::Error;
 use std::sync::{Arc, RwLock, RwLockWriteGuard, RwLockReadGuard};
 use std::collections::{HashMap, HashSet};
+use std::time::{Duration, SystemTime};
 use tonic::{Request, Response, Status};
 use crate::user::framework::PostgresUserRepository;
 use crate::app::framework::PostgresAppRepository;
 use crate::directory::framework::MongoDirectoryRepository;
 use crate::security;
-use crate::constants::{settings, errors};
+use crate::time::unix_timestamp;
+use crate::config::ConfigStore;
+use crate::constants::errors;
 use super::domain::{Session, SessionRepository};
 use super::application::{GroupByAppRepository, get_writable_session};
 
@@ -26,20 +29,23 @@ pub struct SessionServiceImplementation {
     sess_repo: &'static InMemorySessionRepository,
     user_repo: &'static PostgresUserRepository,
     app_repo: &'static PostgresAppRepository,
-    dir_repo: &'static MongoDirectoryRepository
+    dir_repo: &'static MongoDirectoryRepository,
+    config: &'static ConfigStore,
 }
 
 impl SessionServiceImplementation {
     pub fn new(sess_repo: &'static InMemorySessionRepository,
                user_repo: &'static PostgresUserRepository,
                app_repo: &'static PostgresAppRepository,
-               dir_repo: &'static MongoDirectoryRepository) -> Self {
-        
+               dir_repo: &'static MongoDirectoryRepository,
+               config: &'static ConfigStore) -> Self {
+
         SessionServiceImplementation {
             sess_repo: sess_repo,
             user_repo: user_repo,
             app_repo: app_repo,
             dir_repo: dir_repo,
+            config: config,
         }
     }
 }
@@ -49,10 +55,18 @@ impl SessionService for SessionServiceImplementation {
     async fn login(&self, request: Request<LoginRequest>) -> Result<Response<LoginResponse>, Status> {
         let msg_ref = request.into_inner();
 
+        // reads whatever config was last (re)loaded, so a session created by this very request
+        // picks up a TOKEN_LEN/TTL change made after the server started without a restart
+        let config = match self.config.get() {
+            Err(err) => return Err(Status::internal(err.to_string())),
+            Ok(config) => config,
+        };
+
         match super::application::session_login(&self.sess_repo,
                                                 &self.user_repo,
                                                 &self.app_repo,
                                                 &self.dir_repo,
+                                                &config,
                                                 &msg_ref.ident,
                                                 &msg_ref.pwd,
                                                 &msg_ref.totp,
@@ -94,10 +108,11 @@ impl SessionService for SessionServiceImplementation {
 pub struct InMemorySessionRepository {
     all_instances: RwLock<HashMap<String, Arc<RwLock<Session>>>>,
     group_by_app: RwLock<HashMap<String, Arc<RwLock<HashSet<String>>>>>,
+    config: &'static ConfigStore,
 }
 
 impl InMemorySessionRepository {
-    pub fn new() -> Self {
+    pub fn new(config: &'static ConfigStore) -> Self {
         InMemorySessionRepository {
             all_instances: {
                 let repo = HashMap::new();
@@ -108,6 +123,8 @@ impl InMemorySessionRepository {
                 let repo = HashMap::new();
                 RwLock::new(repo)
             },
+
+            config: config,
         }
     }
 
@@ -211,7 +228,77 @@ impl InMemorySessionRepository {
         Ok(())
     }
 
-    pub fn delete_all_by_app(&self, url: &str) -> Result<(), Box<dyn Error>> {    
+    fn touch_last_seen(sess: &Arc<RwLock<Session>>) -> Result<(), Box<dyn Error>> {
+        let mut session = get_writable_session(sess)?;
+        session.last_seen = unix_timestamp(SystemTime::now());
+        Ok(())
+    }
+
+    fn is_expired(sess: &Arc<RwLock<Session>>, now: usize, idle_ttl: usize) -> bool {
+        match sess.read() {
+            Ok(session) => {
+                now >= session.expires_at || now.saturating_sub(session.last_seen) >= idle_ttl
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Scans `all_instances` for sessions past their absolute or idle TTL, deletes them, and
+    /// cleans their URLs out of `group_by_app`. SIDs are collected in a single read pass so the
+    /// write lock over `all_instances` is never held while walking `group_by_app`. The idle TTL
+    /// is read from the live config on every sweep, so a reload takes effect on existing sessions
+    /// too, unlike the absolute TTL which is fixed into each session's `expires_at` at creation.
+    pub fn sweep_expired(&self) -> Result<usize, Box<dyn Error>> {
+        let now = unix_timestamp(SystemTime::now());
+        let idle_ttl = self.config.get()?.session_idle_ttl;
+
+        let expired: Vec<(String, Vec<String>)> = {
+            let repo = self.get_readable_repo()?;
+            repo.iter()
+                .filter(|(_, sess)| InMemorySessionRepository::is_expired(sess, now, idle_ttl))
+                .filter_map(|(sid, sess)| {
+                    sess.read().ok().map(|session| (sid.clone(), session.directory_urls()))
+                })
+                .collect()
+        };
+
+        if expired.is_empty() {
+            return Ok(0);
+        }
+
+        {
+            let mut repo = self.get_writable_repo()?;
+            for (sid, _) in &expired {
+                repo.remove(sid);
+            }
+        }
+
+        for (sid, urls) in &expired {
+            for url in urls {
+                if let Err(err) = self.remove(url, sid) {
+                    warn!("removing swept session {} from group {}: {}", sid, url, err);
+                }
+            }
+        }
+
+        Ok(expired.len())
+    }
+
+    /// Spawns a background thread that calls `sweep_expired` every `interval`, for as long as
+    /// `self` (expected to be the `'static` singleton from `get_repository`) lives.
+    pub fn run_expiry_sweeper(&'static self, interval: Duration) {
+        std::thread::spawn(move || loop {
+            std::thread::sleep(interval);
+
+            match self.sweep_expired() {
+                Ok(0) => {}
+                Ok(swept) => info!("expiry sweeper removed {} expired session(s)", swept),
+                Err(err) => error!("expiry sweeper failed: {}", err),
+            }
+        });
+    }
+
+    pub fn delete_all_by_app(&self, url: &str) -> Result<(), Box<dyn Error>> {
         { // write lock is released at the end of this block
             let group = self.get_readable_group()?;
             let sids_search = group.get(url);
@@ -240,6 +327,7 @@ impl SessionRepository for &InMemorySessionRepository {
     fn find(&self, token: &str) -> Result<Arc<RwLock<Session>>, Box<dyn Error>> {
         let repo = self.get_readable_repo()?;
         if let Some(sess) = repo.get(token) {
+            InMemorySessionRepository::touch_last_seen(sess)?;
             return Ok(Arc::clone(sess));
         }
 
@@ -249,6 +337,7 @@ impl SessionRepository for &InMemorySessionRepository {
     fn find_by_email(&self, email: &str) -> Result<Arc<RwLock<Session>>, Box<dyn Error>> {
         let repo = self.get_readable_repo()?;
         if let Some((_, sess)) = repo.iter().find(|(_, sess)| InMemorySessionRepository::session_has_email(sess, email)) {
+            InMemorySessionRepository::touch_last_seen(sess)?;
             return Ok(Arc::clone(sess));
         }
 
@@ -265,14 +354,21 @@ impl SessionRepository for &InMemorySessionRepository {
             return Err(errors::ALREADY_EXISTS.into());
         }
 
+        let config = self.config.get()?;
+
         loop { // make sure the token is unique
-            let sid = security::get_random_string(settings::TOKEN_LEN);
+            let sid = security::get_random_string(config.token_len);
             if repo.get(&sid).is_none() {
                 session.sid = sid;
                 break;
             }
         }
-        
+
+        let now = unix_timestamp(SystemTime::now());
+        session.created_at = now;
+        session.last_seen = now;
+        session.expires_at = now + config.session_absolute_ttl;
+
         let token = session.sid.clone();
         let mu = RwLock::new(session);
         let arc = Arc::new(mu);
@@ -341,4 +437,49 @@ impl GroupByAppRepository for &InMemorySessionRepository {
 
         Ok(())
     }
+}
+
+// `sweep_expired` itself is not covered here: exercising it needs an `Arc<RwLock<Session>>` in
+// `all_instances`, and `Session` (defined in `super::domain`) is not present in this tree to
+// construct one. `GroupByAppRepository::remove`, the cleanup step `sweep_expired` reuses once it
+// has collected expired SIDs, only ever deals in plain `url`/`sid` strings, so that part of the
+// sweep is covered below.
+#[cfg(test)]
+mod test {
+    use super::{GroupByAppRepository, InMemorySessionRepository};
+    use crate::config::{Config, ConfigStore};
+
+    fn new_repo() -> &'static InMemorySessionRepository {
+        let config: &'static ConfigStore = Box::leak(Box::new(ConfigStore::new(Config::default())));
+        Box::leak(Box::new(InMemorySessionRepository::new(config)))
+    }
+
+    #[test]
+    fn remove_the_only_sid_should_destroy_the_group() {
+        let repo = new_repo();
+
+        repo.store("https://example.com", "sid-1").unwrap();
+        repo.remove("https://example.com", "sid-1").unwrap();
+
+        let result = repo.get("https://example.com");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn remove_one_of_several_sids_should_keep_the_group() {
+        let repo = new_repo();
+
+        repo.store("https://example.com", "sid-1").unwrap();
+        repo.store("https://example.com", "sid-2").unwrap();
+        repo.remove("https://example.com", "sid-1").unwrap();
+
+        let sids = repo.get("https://example.com").unwrap();
+        assert_eq!(sids.read().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn remove_from_an_unknown_group_should_not_fail() {
+        let repo = new_repo();
+        repo.remove("https://unknown.example.com", "sid-1").unwrap();
+    }
 }
\ No newline at end of file
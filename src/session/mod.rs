@@ -1,12 +1,19 @@
 pub mod framework;
 pub mod application;
+pub mod authorization;
 pub mod domain;
+pub mod scope;
+pub mod permission;
 
 lazy_static! {
+    pub static ref CONFIG_PROVIDER: crate::config::ConfigStore = {
+        crate::config::ConfigStore::new(crate::config::Config::default())
+    };
+
     static ref REPO_PROVIDER: framework::InMemorySessionRepository = {
-        framework::InMemorySessionRepository::new()
-    }; 
-}   
+        framework::InMemorySessionRepository::new(&CONFIG_PROVIDER)
+    };
+}
 
 pub fn get_repository() -> Box<&'static dyn domain::SessionRepository> {
     Box::new(&*REPO_PROVIDER)
@@ -0,0 +1,233 @@
+use std::error::Error;
+use diesel::NotFound;
+use diesel::result::Error as PgError;
+
+use crate::diesel::prelude::*;
+use crate::postgres::*;
+use crate::schema::permissions;
+use crate::schema::permissions::dsl::*;
+
+use super::scope::ScopeSet;
+
+pub trait PermissionRepository {
+    fn find_by_user_and_app(&self, target_user_id: i32, target_app_id: &str) -> Result<Permission, Box<dyn Error>>;
+    fn find_default_by_app(&self, target_app_id: &str) -> Result<Permission, Box<dyn Error>>;
+    fn create(&self, permission: &mut Permission) -> Result<(), Box<dyn Error>>;
+    fn save(&self, permission: &Permission) -> Result<(), Box<dyn Error>>;
+    fn delete(&self, permission: &Permission) -> Result<(), Box<dyn Error>>;
+}
+
+/// The scopes a given user has been granted against a given app (OAuth client). A `user_id` of
+/// `None` denotes the app's own default grant, which applies to any user who has no explicit
+/// `Permission` row for the app.
+pub struct Permission {
+    pub id: i32,
+    pub user_id: Option<i32>,
+    pub app_id: String,
+    pub scope: ScopeSet,
+}
+
+impl Permission {
+    pub fn new(user_id: i32, app_id: &str, scope: ScopeSet) -> Self {
+        Permission {
+            id: 0,
+            user_id: Some(user_id),
+            app_id: app_id.to_string(),
+            scope,
+        }
+    }
+
+    pub fn default_for_app(app_id: &str, scope: ScopeSet) -> Self {
+        Permission {
+            id: 0,
+            user_id: None,
+            app_id: app_id.to_string(),
+            scope,
+        }
+    }
+
+    /// Resolves the scopes explicitly granted to `user_id` for `app_id` from `repo`: an explicit
+    /// `Permission` row wins; absent one, falls back to the app's own default scope set. Returns
+    /// `None` when neither exists, rather than silently handing back an empty `ScopeSet`, so a
+    /// caller can tell "this (user, app) pair has no permission model configured yet" apart from
+    /// "this (user, app) pair was explicitly granted nothing" and fall back to a different scope
+    /// source accordingly.
+    pub fn resolve(repo: &impl PermissionRepository, user_id: i32, app_id: &str) -> Option<ScopeSet> {
+        repo.find_by_user_and_app(user_id, app_id)
+            .or_else(|_| repo.find_default_by_app(app_id))
+            .map(|permission| permission.scope)
+            .ok()
+    }
+}
+
+#[derive(Queryable, Insertable, Identifiable, AsChangeset)]
+#[changeset_options(treat_none_as_null = "true")]
+#[table_name = "permissions"]
+struct PostgresPermission {
+    pub id: i32,
+    pub user_id: Option<i32>,
+    pub app_id: String,
+    pub scope: String,
+}
+
+#[derive(Insertable)]
+#[table_name = "permissions"]
+struct NewPostgresPermission<'a> {
+    pub user_id: Option<i32>,
+    pub app_id: &'a str,
+    pub scope: &'a str,
+}
+
+pub struct PostgresPermissionRepository;
+
+impl PostgresPermissionRepository {
+    fn build_first(results: &[PostgresPermission]) -> Result<Permission, Box<dyn Error>> {
+        if results.len() == 0 {
+            return Err(Box::new(NotFound));
+        }
+
+        Ok(Permission {
+            id: results[0].id,
+            user_id: results[0].user_id,
+            app_id: results[0].app_id.clone(),
+            scope: ScopeSet::parse(&results[0].scope),
+        })
+    }
+}
+
+impl PermissionRepository for PostgresPermissionRepository {
+    fn find_by_user_and_app(&self, target_user_id: i32, target_app_id: &str) -> Result<Permission, Box<dyn Error>> {
+        let results = { // block is required because of connection release
+            let connection = get_connection().get()?;
+            permissions.filter(user_id.eq(Some(target_user_id)).and(app_id.eq(target_app_id)))
+                 .load::<PostgresPermission>(&connection)?
+        };
+
+        PostgresPermissionRepository::build_first(&results)
+    }
+
+    fn find_default_by_app(&self, target_app_id: &str) -> Result<Permission, Box<dyn Error>> {
+        let results = { // block is required because of connection release
+            let connection = get_connection().get()?;
+            permissions.filter(app_id.eq(target_app_id).and(user_id.is_null()))
+                 .load::<PostgresPermission>(&connection)?
+        };
+
+        PostgresPermissionRepository::build_first(&results)
+    }
+
+    fn create(&self, permission: &mut Permission) -> Result<(), Box<dyn Error>> {
+        let new_permission = NewPostgresPermission {
+            user_id: permission.user_id,
+            app_id: &permission.app_id,
+            scope: &permission.scope.to_string(),
+        };
+
+        let connection = get_connection().get()?;
+        let result = diesel::insert_into(permissions::table)
+            .values(&new_permission)
+            .get_result::<PostgresPermission>(&connection)?;
+
+        permission.id = result.id;
+        Ok(())
+    }
+
+    fn save(&self, permission: &Permission) -> Result<(), Box<dyn Error>> {
+        let pg_permission = PostgresPermission {
+            id: permission.id,
+            user_id: permission.user_id,
+            app_id: permission.app_id.clone(),
+            scope: permission.scope.to_string(),
+        };
+
+        let connection = get_connection().get()?;
+        diesel::update(permissions)
+            .filter(id.eq(permission.id))
+            .set(&pg_permission)
+            .execute(&connection)?;
+
+        Ok(())
+    }
+
+    fn delete(&self, permission: &Permission) -> Result<(), Box<dyn Error>> {
+        let connection = get_connection().get()?;
+        diesel::delete(permissions.filter(id.eq(permission.id))).execute(&connection)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{Permission, PermissionRepository};
+    use std::error::Error;
+    use std::fmt;
+
+    #[derive(Debug)]
+    pub struct NotFound;
+
+    impl fmt::Display for NotFound {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "permission not found")
+        }
+    }
+
+    impl Error for NotFound {}
+
+    type FindFn = fn(&PermissionRepositoryMock, i32, &str) -> Result<Permission, Box<dyn Error>>;
+
+    /// Mirrors [`PostgresPermissionRepository`](super::PostgresPermissionRepository): every hook
+    /// defaults to reporting `NotFound`, so a test only needs to override the lookups it cares
+    /// about.
+    #[derive(Default)]
+    pub struct PermissionRepositoryMock {
+        pub fn_find_by_user_and_app: Option<FindFn>,
+        pub fn_find_default_by_app: Option<FindFn>,
+    }
+
+    impl PermissionRepository for PermissionRepositoryMock {
+        fn find_by_user_and_app(&self, target_user_id: i32, target_app_id: &str) -> Result<Permission, Box<dyn Error>> {
+            match self.fn_find_by_user_and_app {
+                Some(f) => f(self, target_user_id, target_app_id),
+                None => Err(Box::new(NotFound)),
+            }
+        }
+
+        fn find_default_by_app(&self, target_app_id: &str) -> Result<Permission, Box<dyn Error>> {
+            match self.fn_find_default_by_app {
+                Some(f) => f(self, 0, target_app_id),
+                None => Err(Box::new(NotFound)),
+            }
+        }
+
+        fn create(&self, _permission: &mut Permission) -> Result<(), Box<dyn Error>> {
+            Ok(())
+        }
+
+        fn save(&self, _permission: &Permission) -> Result<(), Box<dyn Error>> {
+            Ok(())
+        }
+
+        fn delete(&self, _permission: &Permission) -> Result<(), Box<dyn Error>> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn resolve_falls_back_to_default_for_app() {
+        let repo = PermissionRepositoryMock {
+            fn_find_by_user_and_app: Some(|_, _, _| Err(Box::new(NotFound))),
+            fn_find_default_by_app: Some(|_, _, app_id| {
+                Ok(Permission::default_for_app(app_id, super::ScopeSet::parse("directory:read")))
+            }),
+        };
+
+        let resolved = Permission::resolve(&repo, 999, "some-app").expect("a default permission exists");
+        assert_eq!(resolved, super::ScopeSet::parse("directory:read"));
+    }
+
+    #[test]
+    fn resolve_is_none_when_nothing_configured_for_app() {
+        let repo = PermissionRepositoryMock::default();
+        assert!(Permission::resolve(&repo, 999, "some-app").is_none());
+    }
+}
@@ -0,0 +1,126 @@
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// A single OAuth scope, e.g. `directory:read`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Scope(String);
+
+impl Scope {
+    pub fn new(name: &str) -> Self {
+        Scope(name.to_string())
+    }
+
+    /// Whether this (granted) scope covers `required`, supporting a trailing `:*` as a
+    /// hierarchical wildcard: `directory:*` implies `directory:read` and `directory:write:meta`
+    /// alike, by matching on `required`'s colon-separated prefix.
+    fn implies(&self, required: &Scope) -> bool {
+        if self == required {
+            return true;
+        }
+
+        match self.0.strip_suffix(":*") {
+            Some(prefix) => required.0 == prefix || required.0.starts_with(&format!("{}:", prefix)),
+            None => false,
+        }
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// A set of scopes, serialized as a single space-delimited string in the JWT payload, per OAuth
+/// convention.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ScopeSet(BTreeSet<Scope>);
+
+impl ScopeSet {
+    pub fn parse(raw: &str) -> Self {
+        ScopeSet(raw.split_whitespace().map(Scope::new).collect())
+    }
+
+    /// Restricts `self` to the scopes also present in `granted`, so a login request can never
+    /// be issued more than what the user/client is actually allowed.
+    pub fn intersect(&self, granted: &ScopeSet) -> ScopeSet {
+        ScopeSet(self.0.intersection(&granted.0).cloned().collect())
+    }
+
+    pub fn covers(&self, required: &[Scope]) -> bool {
+        required
+            .iter()
+            .all(|req| self.0.iter().any(|granted| granted.implies(req)))
+    }
+}
+
+impl fmt::Display for ScopeSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let joined = self
+            .0
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        write!(f, "{}", joined)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn implies_should_match_identical_scope() {
+        assert!(Scope::new("directory:read").implies(&Scope::new("directory:read")));
+    }
+
+    #[test]
+    fn implies_should_not_match_unrelated_scope() {
+        assert!(!Scope::new("directory:read").implies(&Scope::new("directory:write")));
+    }
+
+    #[test]
+    fn implies_wildcard_should_match_direct_and_nested_children() {
+        let wildcard = Scope::new("directory:*");
+
+        assert!(wildcard.implies(&Scope::new("directory:read")));
+        assert!(wildcard.implies(&Scope::new("directory:write:meta")));
+        assert!(wildcard.implies(&Scope::new("directory")));
+    }
+
+    #[test]
+    fn implies_wildcard_should_not_match_sibling_prefix() {
+        let wildcard = Scope::new("directory:*");
+        assert!(!wildcard.implies(&Scope::new("directory_extra:read")));
+    }
+
+    #[test]
+    fn covers_should_require_every_required_scope_to_be_implied() {
+        let granted = ScopeSet::parse("directory:read session:write");
+
+        assert!(granted.covers(&[Scope::new("directory:read")]));
+        assert!(!granted.covers(&[Scope::new("directory:read"), Scope::new("directory:write")]));
+    }
+
+    #[test]
+    fn covers_should_be_wildcard_aware() {
+        let granted = ScopeSet::parse("directory:*");
+        assert!(granted.covers(&[Scope::new("directory:read"), Scope::new("directory:write:meta")]));
+    }
+
+    #[test]
+    fn covers_with_no_required_scopes_should_not_fail() {
+        let granted = ScopeSet::parse("directory:read");
+        assert!(granted.covers(&[]));
+    }
+
+    #[test]
+    fn intersect_should_restrict_to_scopes_present_in_both() {
+        let requested = ScopeSet::parse("directory:read directory:write");
+        let granted = ScopeSet::parse("directory:read session:write");
+
+        assert_eq!(requested.intersect(&granted), ScopeSet::parse("directory:read"));
+    }
+}
@@ -1,4 +1,6 @@
 use regex::Regex;
+use std::collections::HashSet;
+use std::net::{IpAddr, ToSocketAddrs};
 
 const REGEX_NAME: &str = r"^[a-zA-Z]+$";
 const REGEX_EMAIL: &str = r"^[A-Z0-9._%+-]+@[A-Z0-9.-]+\.[A-Z]{2,63}$";
@@ -11,6 +13,7 @@ const ERR_EMAIL_FORMAT: &str = "The provided email does not match with any real
 const ERR_PWD_FORMAT: &str = "The password must contains, at least, an upper and lower case letters, as well as some numbers and special characters";
 const ERR_DATA_FORMAT: &str = "The provided data does not match with base 64 format";
 const ERR_URL_FORMAT: &str = "The provided string does not match with the url standard";
+const ERR_URL_BLOCKED: &str = "The provided url resolves to a non-routable or disallowed address";
 
 pub fn check_name(name: &str) -> Result<(), &str> {
     let regex = Regex::new(REGEX_NAME).unwrap();
@@ -55,4 +58,244 @@ pub fn check_url(data: &str) -> Result<(), &str> {
     }
 
     Ok(())
+}
+
+/// Resolves hostnames to IP addresses so a URL's target can be classified without trusting
+/// ambient DNS configuration. Swappable so deployments can pin trusted nameservers instead of
+/// relying on `/etc/resolv.conf`.
+pub trait DnsResolver {
+    fn resolve(&self, host: &str) -> Result<Vec<IpAddr>, &'static str>;
+}
+
+/// Resolves through the operating system's own resolver.
+pub struct SystemResolver;
+
+impl DnsResolver for SystemResolver {
+    fn resolve(&self, host: &str) -> Result<Vec<IpAddr>, &'static str> {
+        let addrs = (host, 0).to_socket_addrs().map_err(|_| ERR_URL_BLOCKED)?;
+        Ok(addrs.map(|addr| addr.ip()).collect())
+    }
+}
+
+/// Host/CIDR allow- and deny-lists applied on top of the default non-global-address rejection.
+/// A non-empty `allow_hosts` restricts accepted URLs to the hosts it lists, whose addresses are
+/// still subject to the non-global check; an address in `allow_cidrs` is accepted even if it
+/// would otherwise be classified as non-global. `deny_hosts`/`deny_cidrs` are checked first and
+/// always win.
+#[derive(Default)]
+pub struct UrlPolicy {
+    pub allow_hosts: HashSet<String>,
+    pub deny_hosts: HashSet<String>,
+    pub allow_cidrs: Vec<(IpAddr, u8)>,
+    pub deny_cidrs: Vec<(IpAddr, u8)>,
+}
+
+fn extract_host(url: &str) -> Option<&str> {
+    let authority = url.splitn(2, "://").nth(1)?.split(['/', '?', '#']).next()?;
+    let authority = authority.rsplit('@').next()?; // strip userinfo, if any
+
+    if let Some(rest) = authority.strip_prefix('[') {
+        return rest.split(']').next(); // bracketed IPv6 literal, e.g. [::1]:8080
+    }
+
+    authority.split(':').next()
+}
+
+fn cidr_contains(cidr: &(IpAddr, u8), addr: &IpAddr) -> bool {
+    match (cidr.0, addr) {
+        (IpAddr::V4(net), IpAddr::V4(addr)) => {
+            let shift = 32 - cidr.1.min(32);
+            let mask = if shift == 32 { 0 } else { u32::MAX << shift };
+            (u32::from(net) & mask) == (u32::from(*addr) & mask)
+        }
+        (IpAddr::V6(net), IpAddr::V6(addr)) => {
+            let shift = 128 - cidr.1.min(128);
+            let mask = if shift == 128 { 0 } else { u128::MAX << shift };
+            (u128::from(net) & mask) == (u128::from(*addr) & mask)
+        }
+        _ => false,
+    }
+}
+
+/// An address is considered non-routable for SSRF purposes if it is loopback, link-local,
+/// private (RFC 1918), unique-local IPv6, or otherwise not expected to be reachable from the
+/// public internet.
+fn is_non_global(addr: &IpAddr) -> bool {
+    match addr {
+        IpAddr::V4(v4) => {
+            v4.is_loopback()
+                || v4.is_link_local()
+                || v4.is_private()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                || v4.is_documentation()
+                || v4.is_multicast()
+        }
+        IpAddr::V6(v6) => {
+            let is_unique_local = (v6.segments()[0] & 0xfe00) == 0xfc00;
+            let is_unicast_link_local = (v6.segments()[0] & 0xffc0) == 0xfe80;
+
+            v6.is_loopback() || v6.is_unspecified() || v6.is_multicast() || is_unique_local || is_unicast_link_local
+        }
+    }
+}
+
+fn is_blocked_addr(addr: &IpAddr, policy: &UrlPolicy) -> bool {
+    if policy.deny_cidrs.iter().any(|cidr| cidr_contains(cidr, addr)) {
+        return true;
+    }
+
+    if policy.allow_cidrs.iter().any(|cidr| cidr_contains(cidr, addr)) {
+        return false;
+    }
+
+    is_non_global(addr)
+}
+
+fn check_host_policy(host: &str, policy: &UrlPolicy) -> Result<(), &'static str> {
+    if policy.deny_hosts.contains(host) {
+        return Err(ERR_URL_BLOCKED);
+    }
+
+    if !policy.allow_hosts.is_empty() && !policy.allow_hosts.contains(host) {
+        return Err(ERR_URL_BLOCKED);
+    }
+
+    Ok(())
+}
+
+/// Hardened mode of [`check_url`]: validates the format as usual, then rejects `data` if its
+/// host is deny-listed, isn't allow-listed (when an allow-list is configured), or is itself a
+/// literal IP address that is non-global and not explicitly allowed.
+pub fn check_url_hardened(data: &str, policy: &UrlPolicy) -> Result<(), &'static str> {
+    check_url(data)?;
+    let host = extract_host(data).ok_or(ERR_URL_FORMAT)?;
+    check_host_policy(host, policy)?;
+
+    if let Ok(addr) = host.parse::<IpAddr>() {
+        if is_blocked_addr(&addr, policy) {
+            return Err(ERR_URL_BLOCKED);
+        }
+    }
+
+    Ok(())
+}
+
+/// Like [`check_url_hardened`], but for a hostname (as opposed to a literal IP) also resolves it
+/// through `resolver` and rejects `data` if any resolved address is blocked. This is what
+/// closes the DNS-rebinding gap a format-only or literal-IP-only check leaves open.
+pub fn resolve_and_check_url(data: &str, policy: &UrlPolicy, resolver: &dyn DnsResolver) -> Result<(), &'static str> {
+    check_url_hardened(data, policy)?;
+    let host = extract_host(data).ok_or(ERR_URL_FORMAT)?;
+
+    if host.parse::<IpAddr>().is_ok() {
+        return Ok(()); // already resolved and checked by check_url_hardened
+    }
+
+    let addrs = resolver.resolve(host)?;
+    if addrs.is_empty() {
+        return Err(ERR_URL_BLOCKED);
+    }
+
+    if addrs.iter().any(|addr| is_blocked_addr(addr, policy)) {
+        return Err(ERR_URL_BLOCKED);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_non_global_should_reject_loopback_private_and_link_local() {
+        assert!(is_non_global(&"127.0.0.1".parse().unwrap()));
+        assert!(is_non_global(&"10.0.0.1".parse().unwrap()));
+        assert!(is_non_global(&"192.168.1.1".parse().unwrap()));
+        assert!(is_non_global(&"169.254.0.1".parse().unwrap()));
+        assert!(is_non_global(&"::1".parse().unwrap()));
+        assert!(is_non_global(&"fc00::1".parse().unwrap()));
+        assert!(is_non_global(&"fe80::1".parse().unwrap()));
+    }
+
+    #[test]
+    fn is_non_global_should_accept_public_addresses() {
+        assert!(!is_non_global(&"8.8.8.8".parse().unwrap()));
+        assert!(!is_non_global(&"2001:4860:4860::8888".parse().unwrap()));
+    }
+
+    #[test]
+    fn is_blocked_addr_should_respect_deny_cidr_over_otherwise_global_address() {
+        let policy = UrlPolicy {
+            deny_cidrs: vec![("8.8.8.0".parse().unwrap(), 24)],
+            ..Default::default()
+        };
+
+        assert!(is_blocked_addr(&"8.8.8.8".parse().unwrap(), &policy));
+        assert!(!is_blocked_addr(&"8.8.9.8".parse().unwrap(), &policy));
+    }
+
+    #[test]
+    fn is_blocked_addr_should_let_allow_cidr_override_non_global_classification() {
+        let policy = UrlPolicy {
+            allow_cidrs: vec![("10.0.0.0".parse().unwrap(), 8)],
+            ..Default::default()
+        };
+
+        assert!(!is_blocked_addr(&"10.1.2.3".parse().unwrap(), &policy));
+        assert!(is_blocked_addr(&"192.168.1.1".parse().unwrap(), &policy));
+    }
+
+    #[test]
+    fn check_url_hardened_should_reject_non_global_literal_ip() {
+        check_url_hardened("http://127.0.0.1/callback", &UrlPolicy::default()).unwrap_err();
+    }
+
+    #[test]
+    fn check_url_hardened_should_accept_public_literal_ip() {
+        check_url_hardened("http://8.8.8.8/callback", &UrlPolicy::default()).unwrap();
+    }
+
+    #[test]
+    fn check_url_hardened_should_reject_deny_listed_host() {
+        let policy = UrlPolicy {
+            deny_hosts: ["example.com".to_string()].into_iter().collect(),
+            ..Default::default()
+        };
+
+        check_url_hardened("https://example.com/callback", &policy).unwrap_err();
+    }
+
+    #[test]
+    fn check_url_hardened_should_reject_host_missing_from_nonempty_allow_list() {
+        let policy = UrlPolicy {
+            allow_hosts: ["allowed.example.com".to_string()].into_iter().collect(),
+            ..Default::default()
+        };
+
+        check_url_hardened("https://other.example.com/callback", &policy).unwrap_err();
+        check_url_hardened("https://allowed.example.com/callback", &policy).unwrap();
+    }
+
+    struct StaticResolver(Vec<IpAddr>);
+
+    impl DnsResolver for StaticResolver {
+        fn resolve(&self, _host: &str) -> Result<Vec<IpAddr>, &'static str> {
+            Ok(self.0.clone())
+        }
+    }
+
+    #[test]
+    fn resolve_and_check_url_should_reject_host_resolving_to_non_global_address() {
+        let resolver = StaticResolver(vec!["127.0.0.1".parse().unwrap()]);
+        resolve_and_check_url("https://rebinder.example.com/callback", &UrlPolicy::default(), &resolver)
+            .unwrap_err();
+    }
+
+    #[test]
+    fn resolve_and_check_url_should_accept_host_resolving_to_public_address() {
+        let resolver = StaticResolver(vec!["93.184.216.34".parse().unwrap()]);
+        resolve_and_check_url("https://example.com/callback", &UrlPolicy::default(), &resolver).unwrap();
+    }
 }
\ No newline at end of file
@@ -0,0 +1,237 @@
+use super::{EventService, MailService, PushService, UserApplication, UserRepository};
+use crate::cache::Cache;
+use crate::crypto;
+use crate::mfa::service::MfaService;
+use crate::secret::application::SecretRepository;
+use crate::token::domain::{Claims, Token, TokenKind};
+use crate::token::service::TokenService;
+use crate::user::domain::{Password, PasswordHash, Salt};
+use crate::user::error::{Error, Result};
+
+const API_KEY_SECRET_LEN: usize = 32;
+
+/// A stored, hashed API key: everything needed to verify a presented secret and to report the
+/// key back to its owner, minus the plaintext secret itself, which is never persisted.
+pub struct ApiKeyRecord {
+    pub id: i32,
+    pub user_id: i32,
+    pub name: String,
+    pub scope: String,
+    pub secret_hash: PasswordHash,
+    pub salt: Salt,
+}
+
+/// Returned once, at issuance or rotation time, since the plaintext secret cannot be recovered
+/// afterwards: `id` is what a caller references in `revoke_api_key`/`rotate_api_key`, `key` is
+/// the `"{id}.{secret}"` credential to present for authentication.
+pub struct IssuedApiKey {
+    pub id: i32,
+    pub key: String,
+}
+
+fn generate_api_secret() -> Password {
+    let secret = format!(
+        "{}{}&",
+        crypto::get_random_string(API_KEY_SECRET_LEN / 2).to_lowercase(),
+        crypto::get_random_string(API_KEY_SECRET_LEN / 2).to_uppercase(),
+    );
+
+    secret.try_into().expect("generated api secret must satisfy Password's invariants")
+}
+
+fn split_api_key(key: &str) -> Result<(i32, &str)> {
+    let (id, secret) = key.split_once('.').ok_or(Error::WrongCredentials)?;
+    let id = id.parse().map_err(|_| Error::WrongCredentials)?;
+    Ok((id, secret))
+}
+
+impl<U, S, T, F, M, B, C, P> UserApplication<U, S, T, F, M, B, C, P>
+where
+    U: UserRepository,
+    S: SecretRepository,
+    T: TokenService,
+    F: MfaService,
+    M: MailService,
+    B: EventService,
+    C: Cache,
+    P: PushService,
+{
+    /// Issues a long-lived API key for the user behind `session`, labeled `name` and restricted
+    /// to `scope`. Only the Argon2 hash of the generated secret is persisted; the plaintext is
+    /// returned once and cannot be recovered afterwards.
+    #[instrument(skip(self, session))]
+    pub async fn issue_api_key(&self, session: Token, name: &str, scope: &str) -> Result<IssuedApiKey> {
+        let claims = self.token_srv.claims(session).await?;
+        if !claims.payload().kind().is_session() {
+            return Err(Error::WrongToken);
+        }
+
+        let user_id = claims.payload().subject().parse().map_err(|_| Error::WrongToken)?;
+        let secret = generate_api_secret();
+        let salt = Salt::with_length(self.hash_length)?;
+
+        let mut record = ApiKeyRecord {
+            id: 0,
+            user_id,
+            name: name.to_string(),
+            scope: scope.to_string(),
+            secret_hash: PasswordHash::with_salt(&secret, &salt, &self.kdf_params)?,
+            salt,
+        };
+
+        self.user_repo.create_api_key(&mut record).await?;
+
+        Ok(IssuedApiKey {
+            id: record.id,
+            key: format!("{}.{}", record.id, secret.as_ref()),
+        })
+    }
+
+    /// Lists the API keys (without their secrets) owned by the user behind `session`.
+    #[instrument(skip(self, session))]
+    pub async fn list_api_keys(&self, session: Token) -> Result<Vec<ApiKeyRecord>> {
+        let claims = self.token_srv.claims(session).await?;
+        if !claims.payload().kind().is_session() {
+            return Err(Error::WrongToken);
+        }
+
+        let user_id = claims.payload().subject().parse().map_err(|_| Error::WrongToken)?;
+        self.user_repo.list_api_keys(user_id).await
+    }
+
+    /// Permanently invalidates the API key identified by `id`, owned by the user behind `session`.
+    #[instrument(skip(self, session))]
+    pub async fn revoke_api_key(&self, session: Token, id: i32) -> Result<()> {
+        let claims = self.token_srv.claims(session).await?;
+        if !claims.payload().kind().is_session() {
+            return Err(Error::WrongToken);
+        }
+
+        let user_id: i32 = claims.payload().subject().parse().map_err(|_| Error::WrongToken)?;
+        let record = self.user_repo.find_api_key(id).await?;
+        if record.user_id != user_id {
+            return Err(Error::NotFound);
+        }
+
+        self.user_repo.revoke_api_key(id).await
+    }
+
+    /// Atomically replaces the secret behind API key `id`, owned by the user behind `session`,
+    /// with a freshly generated one, invalidating the old secret and returning the new plaintext
+    /// key.
+    #[instrument(skip(self, session))]
+    pub async fn rotate_api_key(&self, session: Token, id: i32) -> Result<IssuedApiKey> {
+        let claims = self.token_srv.claims(session).await?;
+        if !claims.payload().kind().is_session() {
+            return Err(Error::WrongToken);
+        }
+
+        let user_id: i32 = claims.payload().subject().parse().map_err(|_| Error::WrongToken)?;
+        let record = self.user_repo.find_api_key(id).await?;
+        if record.user_id != user_id {
+            return Err(Error::NotFound);
+        }
+
+        let secret = generate_api_secret();
+        let salt = Salt::with_length(self.hash_length)?;
+        let secret_hash = PasswordHash::with_salt(&secret, &salt, &self.kdf_params)?;
+
+        self.user_repo.rotate_api_key(id, &secret_hash, &salt).await?;
+
+        Ok(IssuedApiKey {
+            id,
+            key: format!("{}.{}", id, secret.as_ref()),
+        })
+    }
+
+    /// Authenticates a presented `"{id}.{secret}"` API key, verifying the secret against the
+    /// stored hash in constant time, and issues the same `TokenKind::Session` a password login
+    /// would, so machine clients don't need to hold a refreshable JWT — constrained to the
+    /// scope the key was issued with, not the user's full scope.
+    #[instrument(skip(self, key))]
+    pub async fn authenticate_api_key(&self, key: &str) -> Result<Claims> {
+        let (id, secret) = split_api_key(key)?;
+        let record = self.user_repo.find_api_key(id).await.map_err(|_| Error::WrongCredentials)?;
+
+        let secret: Password = secret.try_into().map_err(|_| Error::WrongCredentials)?;
+        if !record.secret_hash.verify(&secret) {
+            return Err(Error::WrongCredentials);
+        }
+
+        let mut claims = self
+            .token_srv
+            .issue(TokenKind::Session, &record.user_id.to_string())
+            .await?;
+
+        claims.payload = claims.payload.with_scope(&record.scope);
+
+        Ok(claims)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ApiKeyRecord, PasswordHash, Salt};
+    use crate::token::domain::{Claims, Payload, TokenKind};
+    use crate::token::service::test::TokenServiceMock;
+    use crate::user::application::test::{new_user_application, UserRepositoryMock};
+    use crate::user::domain::Password;
+    use crate::user::error::Error;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn stored_record(secret: &Password) -> ApiKeyRecord {
+        let salt = Salt::with_length(32).unwrap();
+        ApiKeyRecord {
+            id: 1,
+            user_id: 999,
+            name: "ci".to_string(),
+            scope: "read:profile".to_string(),
+            secret_hash: PasswordHash::with_salt(secret, &salt, &Default::default()).unwrap(),
+            salt,
+        }
+    }
+
+    #[tokio::test]
+    async fn authenticate_api_key_must_not_fail() {
+        let secret = Password::try_from("abcABC123&".to_string()).unwrap();
+        let record = stored_record(&secret);
+
+        let mut user_repo = UserRepositoryMock::default();
+        user_repo.find_api_key_fn = Some(move |_: &UserRepositoryMock, _: i32| Ok(stored_record(&secret)));
+
+        let mut token_srv = TokenServiceMock::default();
+        token_srv.issue_fn = Some(|_: &TokenServiceMock, kind: TokenKind, sub: &str| {
+            Ok(Claims {
+                token: "abc.abc.abc".to_string().try_into().unwrap(),
+                payload: Payload::new(kind, Duration::from_secs(60)).with_subject(sub),
+            })
+        });
+
+        let mut user_app = new_user_application();
+        user_app.user_repo = Arc::new(user_repo);
+        user_app.token_srv = Arc::new(token_srv);
+
+        let claims = user_app
+            .authenticate_api_key(&format!("{}.abcABC123&", record.id))
+            .await
+            .unwrap();
+
+        assert_eq!(claims.payload().subject(), "999");
+        assert_eq!(claims.payload().scope(), "read:profile");
+    }
+
+    #[tokio::test]
+    async fn authenticate_api_key_with_wrong_secret_should_fail() {
+        let secret = Password::try_from("abcABC123&".to_string()).unwrap();
+
+        let mut user_repo = UserRepositoryMock::default();
+        user_repo.find_api_key_fn = Some(move |_: &UserRepositoryMock, _: i32| Ok(stored_record(&secret)));
+
+        let mut user_app = new_user_application();
+        user_app.user_repo = Arc::new(user_repo);
+
+        let result = user_app.authenticate_api_key("1.wrongSECRET000&").await;
+        assert!(matches!(result, Err(Error::WrongCredentials)), "{:?}", result);
+    }
+}
@@ -0,0 +1,217 @@
+use super::{EventService, MailService, PushService, UserApplication, UserRepository};
+use crate::cache::Cache;
+use crate::constants::settings;
+use crate::crypto;
+use crate::mfa::service::MfaService;
+use crate::secret::application::SecretRepository;
+use crate::token::domain::{Claims, Token, TokenKind};
+use crate::token::service::TokenService;
+use crate::user::error::{Error, Result};
+use std::time::Duration;
+
+const DEVICE_CODE_LEN: usize = 32;
+const USER_CODE_LEN: usize = 8;
+const DEVICE_CODE_TIMEOUT: Duration = Duration::from_secs(600); // 10 minutes
+pub const DEVICE_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum DeviceStatus {
+    Pending,
+    Approved { user_id: String },
+}
+
+#[derive(Serialize, Deserialize)]
+struct DeviceAuthorization {
+    client_id: String,
+    scope: String,
+    status: DeviceStatus,
+    last_polled_at: Option<usize>,
+}
+
+fn device_code_key(device_code: &str) -> String {
+    format!("device_code::{}", device_code)
+}
+
+fn user_code_key(user_code: &str) -> String {
+    format!("user_code::{}", user_code)
+}
+
+pub struct DeviceAuthorizationResponse {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    pub interval: Duration,
+}
+
+pub enum DeviceTokenResult {
+    Pending,
+    SlowDown,
+    Expired,
+    Granted(Claims),
+}
+
+impl<U, S, T, F, M, B, C, P> UserApplication<U, S, T, F, M, B, C, P>
+where
+    U: UserRepository,
+    S: SecretRepository,
+    T: TokenService,
+    F: MfaService,
+    M: MailService,
+    B: EventService,
+    C: Cache,
+    P: PushService,
+{
+    /// Starts the device-authorization grant: generates a `device_code` and a short, human
+    /// friendly `user_code`, caches `{client_id, scope, status: Pending}` under both, and
+    /// returns the pair plus the verification URI and polling interval.
+    #[instrument(skip(self))]
+    pub async fn device_authorize(&self, client_id: &str, scope: &str) -> Result<DeviceAuthorizationResponse> {
+        let device_code = crypto::get_random_string(DEVICE_CODE_LEN);
+        let user_code = crypto::get_random_string(USER_CODE_LEN).to_uppercase();
+
+        let entry = DeviceAuthorization {
+            client_id: client_id.to_string(),
+            scope: scope.to_string(),
+            status: DeviceStatus::Pending,
+            last_polled_at: None,
+        };
+
+        self.cache
+            .save(&device_code_key(&device_code), &entry, DEVICE_CODE_TIMEOUT)
+            .await?;
+
+        self.cache
+            .save(&user_code_key(&user_code), &device_code, DEVICE_CODE_TIMEOUT)
+            .await?;
+
+        Ok(DeviceAuthorizationResponse {
+            device_code,
+            user_code,
+            verification_uri: settings::DEVICE_VERIFICATION_URI.to_string(),
+            interval: DEVICE_POLL_INTERVAL,
+        })
+    }
+
+    /// Polled by the device with its `device_code`. Reports `Pending` while the user has not
+    /// yet approved it from a browser, `SlowDown` if polled faster than the advertised
+    /// interval, `Expired` once the cache entry has been evicted, and `Granted` with a fresh
+    /// session once `device_approve` has run.
+    #[instrument(skip(self))]
+    pub async fn device_token(&self, device_code: &str) -> Result<DeviceTokenResult> {
+        let key = device_code_key(device_code);
+        let mut entry: DeviceAuthorization = match self.cache.find(&key).await {
+            Ok(entry) => entry,
+            Err(_) => return Ok(DeviceTokenResult::Expired),
+        };
+
+        let now = crate::time::unix_timestamp(std::time::SystemTime::now());
+        if let Some(last) = entry.last_polled_at {
+            if now.saturating_sub(last) < DEVICE_POLL_INTERVAL.as_secs() as usize {
+                return Ok(DeviceTokenResult::SlowDown);
+            }
+        }
+
+        let user_id = match &entry.status {
+            DeviceStatus::Pending => {
+                entry.last_polled_at = Some(now);
+                self.cache.save(&key, &entry, DEVICE_CODE_TIMEOUT).await?;
+                return Ok(DeviceTokenResult::Pending);
+            }
+            DeviceStatus::Approved { user_id } => user_id.clone(),
+        };
+
+        self.cache.delete(&key).await?;
+        let mut claims = self.token_srv.issue(TokenKind::Session, &user_id).await?;
+        claims.payload = claims.payload.with_scope(&entry.scope);
+        Ok(DeviceTokenResult::Granted(claims))
+    }
+
+    /// Called by the logged-in user from a browser to complete a device flow: flips the cached
+    /// status to Approved and binds the approving user's id.
+    #[instrument(skip(self, session))]
+    pub async fn device_approve(&self, session: Token, user_code: &str) -> Result<()> {
+        let claims = self.token_srv.claims(session).await?;
+        if !claims.payload().kind().is_session() {
+            return Err(Error::WrongToken);
+        }
+
+        let device_code: String = self
+            .cache
+            .find(&user_code_key(user_code))
+            .await
+            .map_err(|_| Error::NotFound)?;
+
+        let key = device_code_key(&device_code);
+        let mut entry: DeviceAuthorization = self.cache.find(&key).await.map_err(|_| Error::NotFound)?;
+        entry.status = DeviceStatus::Approved {
+            user_id: claims.payload().subject().to_string(),
+        };
+
+        self.cache.save(&key, &entry, DEVICE_CODE_TIMEOUT).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{DeviceTokenResult, TokenKind};
+    use crate::token::domain::{Claims, Payload, Token};
+    use crate::token::service::test::TokenServiceMock;
+    use crate::user::application::test::new_user_application;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn session_token() -> Token {
+        "abc.abc.abc".to_string().try_into().unwrap()
+    }
+
+    fn token_srv_with_session_claims() -> TokenServiceMock {
+        let mut token_srv = TokenServiceMock::default();
+        token_srv.claims_fn = Some(|_: &TokenServiceMock, token: Token| {
+            Ok(Claims {
+                token,
+                payload: Payload::new(TokenKind::Session, Duration::from_secs(60)).with_subject("123"),
+            })
+        });
+
+        token_srv.issue_fn = Some(|_: &TokenServiceMock, kind: TokenKind, sub: &str| {
+            Ok(Claims {
+                token: "abc.abc.abc".to_string().try_into().unwrap(),
+                payload: Payload::new(kind, Duration::from_secs(60)).with_subject(sub),
+            })
+        });
+
+        token_srv
+    }
+
+    #[tokio::test]
+    async fn device_token_should_report_pending_before_approval() {
+        let app = new_user_application();
+        let response = app.device_authorize("client", "read:profile").await.unwrap();
+
+        let result = app.device_token(&response.device_code).await.unwrap();
+        assert!(matches!(result, DeviceTokenResult::Pending));
+    }
+
+    #[tokio::test]
+    async fn device_token_should_report_expired_for_unknown_code() {
+        let app = new_user_application();
+        let result = app.device_token("not-a-real-code").await.unwrap();
+        assert!(matches!(result, DeviceTokenResult::Expired));
+    }
+
+    #[tokio::test]
+    async fn device_token_should_apply_the_requested_scope() {
+        let mut app = new_user_application();
+        app.token_srv = Arc::new(token_srv_with_session_claims());
+
+        let response = app.device_authorize("client", "read:profile").await.unwrap();
+        app.device_approve(session_token(), &response.user_code).await.unwrap();
+
+        let result = app.device_token(&response.device_code).await.unwrap();
+        let DeviceTokenResult::Granted(claims) = result else {
+            panic!("expected DeviceTokenResult::Granted, got a different result");
+        };
+
+        assert_eq!(claims.payload().scope(), "read:profile");
+    }
+}
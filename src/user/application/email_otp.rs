@@ -0,0 +1,117 @@
+use super::{EventBus, MailService, PushService, UserApplication, UserRepository};
+use crate::cache::Cache;
+use crate::constants::settings;
+use crate::mfa::domain::{MfaMethod, Otp};
+use crate::mfa::service::MfaService;
+use crate::secret::application::SecretRepository;
+use crate::token::service::TokenService;
+use crate::user::error::{Error, Result};
+use std::time::Duration;
+
+fn email_otp_key(user_id: i32) -> String {
+    format!("email_otp::{}", user_id)
+}
+
+impl<U, S, T, F, M, B, C, P> UserApplication<U, S, T, F, M, B, C, P>
+where
+    U: UserRepository,
+    S: SecretRepository,
+    T: TokenService,
+    F: MfaService,
+    M: MailService,
+    B: EventBus,
+    C: Cache,
+    P: PushService,
+{
+    /// Generates a short numeric OTP, caches its hash under the user id with a short TTL, and
+    /// emails it. `SessionApplication::login` generates and caches its own OTP the same way the
+    /// first time a `MfaMethod::Email` user hits the step-up branch; this is the enrollment-time
+    /// equivalent, so the user sees a code land in their inbox as soon as email MFA is turned on.
+    #[instrument(skip(self))]
+    pub async fn send_email_otp(&self, user_id: i32) -> Result<()> {
+        let user = self.user_repo.find(user_id).await?;
+        let otp = Otp::generate();
+
+        self.cache
+            .save(
+                &email_otp_key(user_id),
+                &otp.hash(),
+                Duration::from_secs(settings::EMAIL_OTP_TIMEOUT),
+            )
+            .await?;
+
+        self.mail_srv
+            .send_email_otp(&user.credentials.email, &otp)
+            .await
+            .map_err(Error::from)
+    }
+
+    /// Enables `MfaMethod::Email` for the user, sending the first code the user will see so they
+    /// know step-up is live; verifying it is `SessionApplication::login`'s job, not this file's —
+    /// login owns the whole send/verify round trip for `MfaMethod::Email` itself (there is no
+    /// separate device ceremony to wait on, unlike WebAuthn or push), so there is no standalone
+    /// `verify_email_otp` here to call into.
+    #[instrument(skip(self, password))]
+    pub async fn enable_email_mfa(&self, user_id: i32, password: crate::user::domain::Password) -> Result<()> {
+        self.enable_mfa(user_id, MfaMethod::Email, password, None).await?;
+        self.send_email_otp(user_id).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mfa::service::test::MfaServiceMock;
+    use crate::user::application::test::{new_user_application, MailServiceMock, UserRepositoryMock};
+    use crate::user::domain::{Credentials, Email, Password, PasswordHash, Preferences, Salt, User};
+    use std::sync::Arc;
+
+    fn plain_user() -> User {
+        let password = Password::try_from("abcABC123&".to_string()).unwrap();
+        let salt = Salt::with_length(32).unwrap();
+
+        User {
+            id: 999,
+            preferences: Preferences::default(),
+            credentials: Credentials {
+                email: Email::try_from("username@server.domain").unwrap(),
+                password: PasswordHash::with_salt(&password, &salt, &Default::default()).unwrap(),
+            },
+        }
+    }
+
+    fn accepting_mail_srv() -> MailServiceMock {
+        let mut mail_srv = MailServiceMock::default();
+        mail_srv.send_email_otp_fn = Some(|_: &MailServiceMock, _: &Email, _: &crate::mfa::domain::Otp| Ok(()));
+        mail_srv
+    }
+
+    #[tokio::test]
+    async fn send_email_otp_must_not_fail() {
+        let mut user_repo = UserRepositoryMock::default();
+        user_repo.find_fn = Some(|_: &UserRepositoryMock, _: i32| Ok(plain_user()));
+
+        let mut user_app = new_user_application();
+        user_app.user_repo = Arc::new(user_repo);
+        user_app.mail_srv = Arc::new(accepting_mail_srv());
+
+        user_app.send_email_otp(999).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn enable_email_mfa_must_not_fail() {
+        let mut user_repo = UserRepositoryMock::default();
+        user_repo.find_fn = Some(|_: &UserRepositoryMock, _: i32| Ok(plain_user()));
+        user_repo.save_fn = Some(|_: &UserRepositoryMock, _: &mut User| Ok(()));
+
+        let mut mfa_srv = MfaServiceMock::default();
+        mfa_srv.enable_fn = Some(|_: &MfaServiceMock, _: &User, _: Option<&crate::mfa::domain::Otp>| Ok(()));
+
+        let mut user_app = new_user_application();
+        user_app.user_repo = Arc::new(user_repo);
+        user_app.mail_srv = Arc::new(accepting_mail_srv());
+        user_app.multi_factor_srv = Arc::new(mfa_srv);
+
+        let password = Password::try_from("abcABC123&".to_string()).unwrap();
+        user_app.enable_email_mfa(999, password).await.unwrap();
+    }
+}
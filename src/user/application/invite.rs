@@ -0,0 +1,74 @@
+use super::{EventService, MailService, PushService, UserApplication, UserRepository};
+use crate::cache::Cache;
+use crate::crypto;
+use crate::mfa::service::MfaService;
+use crate::secret::application::SecretRepository;
+use crate::token::domain::{Token, TokenKind};
+use crate::token::service::TokenService;
+use crate::user::domain::Email;
+use crate::user::error::{Error, Result};
+
+const INVITE_KEY_LEN: usize = 32;
+
+#[derive(Serialize, Deserialize)]
+pub(super) struct InviteGrant {
+    pub(super) email: Email,
+    pub(super) scope: String,
+}
+
+pub(super) fn invite_key(key: &str) -> String {
+    format!("invite::{}", key)
+}
+
+impl<U, S, T, F, M, B, C, P> UserApplication<U, S, T, F, M, B, C, P>
+where
+    U: UserRepository,
+    S: SecretRepository,
+    T: TokenService,
+    F: MfaService,
+    M: MailService,
+    B: EventService,
+    C: Cache,
+    P: PushService,
+{
+    /// Issues a single-use `TokenKind::Invite` token binding `invitee_email` to `scope` and
+    /// emails it via `MailService::send_invitation_email`. Requires an authenticated `session`
+    /// so only existing users can invite new ones.
+    #[instrument(skip(self, session))]
+    pub async fn invite(&self, session: Token, invitee_email: Email, scope: &str) -> Result<()> {
+        let claims = self.token_srv.claims(session).await?;
+        if !claims.payload().kind().is_session() {
+            return Err(Error::WrongToken);
+        }
+
+        let key = crypto::get_random_string(INVITE_KEY_LEN);
+        let grant = InviteGrant {
+            email: invitee_email.clone(),
+            scope: scope.to_string(),
+        };
+
+        let invite_claims = self.token_srv.issue(TokenKind::Invite, &key).await?;
+
+        self.cache
+            .save(&invite_key(&key), &grant, invite_claims.payload().timeout())
+            .await?;
+
+        self.mail_srv
+            .send_invitation_email(&invitee_email, invite_claims.token())?;
+
+        Ok(())
+    }
+
+    /// Revokes a previously issued invite before it is consumed, deleting its cache entry so the
+    /// token stops being acceptable even if presented before its natural expiry.
+    #[instrument(skip(self, invite_token))]
+    pub async fn revoke_invite(&self, invite_token: Token) -> Result<()> {
+        let claims = self.token_srv.claims(invite_token).await?;
+        if !claims.payload().kind().is_invite() {
+            return Err(Error::WrongToken);
+        }
+
+        self.cache.delete(&invite_key(claims.payload().subject())).await?;
+        self.token_srv.revoke(&claims).await
+    }
+}
@@ -1,6 +1,6 @@
 use std::num::ParseIntError;
 
-use super::{EventBus, MailService, UserApplication, UserRepository};
+use super::{EventBus, MailService, PushService, UserApplication, UserRepository};
 use crate::cache::Cache;
 use crate::mfa::domain::{MfaMethod, Otp};
 use crate::mfa::service::MfaService;
@@ -11,7 +11,7 @@ use crate::token::service::TokenService;
 use crate::user::domain::Password;
 use crate::user::error::{Error, Result};
 
-impl<U, S, T, F, M, B, C> UserApplication<U, S, T, F, M, B, C>
+impl<U, S, T, F, M, B, C, P> UserApplication<U, S, T, F, M, B, C, P>
 where
     U: UserRepository,
     S: SecretRepository,
@@ -20,6 +20,7 @@ where
     M: MailService,
     B: EventBus,
     C: Cache,
+    P: PushService,
 {
     #[instrument(skip(self, password, otp))]
     pub async fn enable_mfa_with_token(
@@ -0,0 +1,234 @@
+use super::{EventService, MailService, PushService, UserApplication, UserRepository};
+use crate::cache::Cache;
+use crate::mfa::service::MfaService;
+use crate::secret::application::SecretRepository;
+use crate::token::service::TokenService;
+use crate::user::error::Result;
+use std::sync::Arc;
+use std::time::Duration;
+
+const DISPATCH_BATCH_SIZE: usize = 50;
+const MAX_DELIVERY_ATTEMPTS: u32 = 5;
+
+/// An event durably queued for later, at-least-once delivery via `dispatch_outbox`. New event
+/// kinds are added here as new write paths gain outbox support.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OutboxEvent {
+    UserCreated,
+    UserPasswordChanged,
+}
+
+/// A row read back from the outbox table: the event itself, the id of the user it concerns, and
+/// how many delivery attempts have already failed.
+pub struct OutboxRecord {
+    pub id: i32,
+    pub user_id: i32,
+    pub event: OutboxEvent,
+    pub retries: u32,
+}
+
+/// Durably queues an `OutboxEvent` for `dispatch_outbox` to deliver. `UserRepository::create`
+/// keeps taking its own `event` directly, since that queueing must happen in the same
+/// transaction as the user row's insert; this trait is for write paths with no such row to
+/// piggyback on, like a password reset that has already saved the user by the time it has an
+/// event to queue.
+pub trait OutboxRepository {
+    async fn enqueue(&self, user_id: i32, event: OutboxEvent) -> Result<()>;
+}
+
+impl<U, S, T, F, M, B, C, P> UserApplication<U, S, T, F, M, B, C, P>
+where
+    U: UserRepository,
+    S: SecretRepository,
+    T: TokenService,
+    F: MfaService,
+    M: MailService,
+    B: EventService,
+    C: Cache,
+    P: PushService,
+{
+    /// Reads one batch of unpublished outbox rows and hands each to the `EventService`. A row
+    /// that fails to publish is left in place with its retry counter incremented, so the next
+    /// call picks it up again, unless it has already exhausted `MAX_DELIVERY_ATTEMPTS`, in which
+    /// case it is marked published anyway to stop it from blocking the batch forever.
+    #[instrument(skip(self))]
+    pub async fn dispatch_outbox(&self) -> Result<usize> {
+        let records = self.user_repo.poll_unpublished_events(DISPATCH_BATCH_SIZE).await?;
+        let dispatched = records.len();
+
+        for record in records {
+            let published = match record.event {
+                OutboxEvent::UserCreated => match self.user_repo.find(record.user_id).await {
+                    Ok(user) => self.event_srv.emit_user_created(&user).await,
+                    Err(err) => Err(err),
+                },
+                OutboxEvent::UserPasswordChanged => match self.user_repo.find(record.user_id).await {
+                    Ok(user) => self.event_srv.emit_user_password_changed(&user).await,
+                    Err(err) => Err(err),
+                },
+            };
+
+            match published {
+                Ok(()) => self.user_repo.mark_event_published(record.id).await?,
+                Err(err) if record.retries + 1 >= MAX_DELIVERY_ATTEMPTS => {
+                    error!(
+                        "outbox record {} exceeded max delivery attempts, dropping: {}",
+                        record.id, err
+                    );
+                    self.user_repo.mark_event_published(record.id).await?;
+                }
+                Err(_) => {
+                    self.user_repo
+                        .mark_event_failed(record.id, record.retries + 1)
+                        .await?;
+                }
+            }
+        }
+
+        Ok(dispatched)
+    }
+
+    /// Runs `dispatch_outbox` on a fixed `interval` until the process exits. Intended to be
+    /// spawned as its own background task alongside the request-serving ones.
+    pub async fn run_outbox_dispatcher(self: Arc<Self>, interval: Duration) {
+        loop {
+            if let Err(err) = self.dispatch_outbox().await {
+                error!("outbox dispatch cycle failed: {}", err);
+            }
+
+            tokio::time::sleep(interval).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{OutboxEvent, OutboxRecord, MAX_DELIVERY_ATTEMPTS};
+    use crate::user::application::test::{new_user_application, EventServiceMock, UserRepositoryMock};
+    use crate::user::domain::{Credentials, Email, Password, PasswordHash, Preferences, Salt, User};
+    use crate::user::error::Error;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    fn one_record(event: OutboxEvent, retries: u32) -> Vec<OutboxRecord> {
+        vec![OutboxRecord { id: 1, user_id: 999, event, retries }]
+    }
+
+    fn user_repo_polling(records: Vec<OutboxRecord>) -> UserRepositoryMock {
+        let mut user_repo = UserRepositoryMock::default();
+        user_repo.poll_unpublished_events_fn = Some(move |_: &UserRepositoryMock, _: usize| Ok(records.clone()));
+        user_repo
+    }
+
+    fn user_with_id(user_id: i32) -> User {
+        let password = Password::try_from("abcABC123&".to_string()).unwrap();
+        let salt = Salt::with_length(32).unwrap();
+
+        User {
+            id: user_id,
+            preferences: Preferences::default(),
+            credentials: Credentials {
+                email: Email::try_from("username@server.domain").unwrap(),
+                password: PasswordHash::with_salt(&password, &salt, &Default::default()).unwrap(),
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn dispatch_outbox_should_publish_a_successfully_delivered_user_created_event() {
+        let mut user_repo = user_repo_polling(one_record(OutboxEvent::UserCreated, 0));
+        user_repo.find_fn = Some(|_: &UserRepositoryMock, user_id: i32| {
+            Ok(user_with_id(user_id))
+        });
+
+        let published = Arc::new(AtomicU32::new(0));
+        let published_flag = published.clone();
+        user_repo.mark_event_published_fn = Some(move |_: &UserRepositoryMock, id: i32| {
+            assert_eq!(id, 1);
+            published_flag.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        });
+
+        let mut event_srv = EventServiceMock::default();
+        event_srv.emit_user_created_fn = Some(|_: &EventServiceMock, _: &User| Ok(()));
+
+        let mut app = new_user_application();
+        app.user_repo = Arc::new(user_repo);
+        app.event_srv = Arc::new(event_srv);
+
+        let dispatched = app.dispatch_outbox().await.unwrap();
+        assert_eq!(dispatched, 1);
+        assert_eq!(published.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn dispatch_outbox_should_publish_a_successfully_delivered_password_changed_event() {
+        let mut user_repo = user_repo_polling(one_record(OutboxEvent::UserPasswordChanged, 0));
+        user_repo.find_fn = Some(|_: &UserRepositoryMock, user_id: i32| {
+            Ok(user_with_id(user_id))
+        });
+        user_repo.mark_event_published_fn = Some(|_: &UserRepositoryMock, _: i32| Ok(()));
+
+        let mut event_srv = EventServiceMock::default();
+        event_srv.emit_user_password_changed_fn = Some(|_: &EventServiceMock, _: &User| Ok(()));
+
+        let mut app = new_user_application();
+        app.user_repo = Arc::new(user_repo);
+        app.event_srv = Arc::new(event_srv);
+
+        let dispatched = app.dispatch_outbox().await.unwrap();
+        assert_eq!(dispatched, 1);
+    }
+
+    #[tokio::test]
+    async fn dispatch_outbox_should_retry_a_failed_delivery_under_the_attempt_limit() {
+        let mut user_repo = user_repo_polling(one_record(OutboxEvent::UserCreated, 0));
+        user_repo.find_fn = Some(|_: &UserRepositoryMock, user_id: i32| {
+            Ok(user_with_id(user_id))
+        });
+
+        let retried_with: Arc<AtomicU32> = Arc::new(AtomicU32::new(0));
+        let retried_flag = retried_with.clone();
+        user_repo.mark_event_failed_fn = Some(move |_: &UserRepositoryMock, id: i32, retries: u32| {
+            assert_eq!(id, 1);
+            retried_flag.store(retries, Ordering::SeqCst);
+            Ok(())
+        });
+
+        let mut event_srv = EventServiceMock::default();
+        event_srv.emit_user_created_fn = Some(|_: &EventServiceMock, _: &User| Err(Error::Debug));
+
+        let mut app = new_user_application();
+        app.user_repo = Arc::new(user_repo);
+        app.event_srv = Arc::new(event_srv);
+
+        app.dispatch_outbox().await.unwrap();
+        assert_eq!(retried_with.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn dispatch_outbox_should_drop_an_event_once_max_attempts_is_exceeded() {
+        let mut user_repo = user_repo_polling(one_record(OutboxEvent::UserCreated, MAX_DELIVERY_ATTEMPTS - 1));
+        user_repo.find_fn = Some(|_: &UserRepositoryMock, user_id: i32| {
+            Ok(user_with_id(user_id))
+        });
+
+        let dropped = Arc::new(AtomicU32::new(0));
+        let dropped_flag = dropped.clone();
+        user_repo.mark_event_published_fn = Some(move |_: &UserRepositoryMock, id: i32| {
+            assert_eq!(id, 1);
+            dropped_flag.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        });
+
+        let mut event_srv = EventServiceMock::default();
+        event_srv.emit_user_created_fn = Some(|_: &EventServiceMock, _: &User| Err(Error::Debug));
+
+        let mut app = new_user_application();
+        app.user_repo = Arc::new(user_repo);
+        app.event_srv = Arc::new(event_srv);
+
+        app.dispatch_outbox().await.unwrap();
+        assert_eq!(dropped.load(Ordering::SeqCst), 1);
+    }
+}
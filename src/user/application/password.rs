@@ -0,0 +1,136 @@
+use super::{EventService, MailService, PushService, UserApplication, UserRepository};
+use crate::cache::Cache;
+use crate::mfa::service::MfaService;
+use crate::secret::application::SecretRepository;
+use crate::token::service::TokenService;
+use crate::user::domain::{Password, Salt, User};
+use crate::user::error::Result;
+
+/// Argon2 cost parameters used to derive `PasswordHash`es. Exposed as configuration so operators
+/// can ratchet up password-hardening over time: a hash produced under weaker parameters is
+/// transparently upgraded the next time its owner's credentials are verified, instead of forcing
+/// a password reset.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct KdfParams {
+    pub memory_cost: u32,
+    pub time_cost: u32,
+    pub parallelism: u32,
+    pub salt_length: usize,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        KdfParams {
+            memory_cost: 19 * 1024, // 19 MiB, the OWASP-recommended Argon2id minimum
+            time_cost: 2,
+            parallelism: 1,
+            salt_length: 16, // 128 bits, the OWASP-recommended Argon2id minimum
+        }
+    }
+}
+
+impl<U, S, T, F, M, B, C, P> UserApplication<U, S, T, F, M, B, C, P>
+where
+    U: UserRepository,
+    S: SecretRepository,
+    T: TokenService,
+    F: MfaService,
+    M: MailService,
+    B: EventService,
+    C: Cache,
+    P: PushService,
+{
+    /// Verifies `password` against `user`'s stored hash. If it matches but was produced under
+    /// weaker Argon2 parameters than `self.kdf_params`, transparently rehashes it with the
+    /// current parameters and persists the upgraded user, so verification keeps working across a
+    /// parameter ratchet without forcing a password reset.
+    #[instrument(skip(self, password))]
+    pub async fn verify_password(&self, user: &mut User, password: &Password) -> Result<bool> {
+        if !user.credentials.password.verify(password) {
+            return Ok(false);
+        }
+
+        if user.credentials.password.needs_rehash(&self.kdf_params) {
+            // salt_length is ratcheted via kdf_params, same as the other Argon2 cost parameters,
+            // rather than the unrelated, untracked self.hash_length used when creating a hash
+            // for the first time (signup, invite, reset, api_key).
+            let salt = Salt::with_length(self.kdf_params.salt_length)?;
+            user.credentials.password = crate::user::domain::PasswordHash::with_salt(password, &salt, &self.kdf_params)?;
+            self.user_repo.save(user).await?;
+        }
+
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::KdfParams;
+    use crate::user::application::test::{new_user_application, UserRepositoryMock};
+    use crate::user::domain::{Credentials, Email, Password, PasswordHash, Preferences, Salt, User};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    fn user_with_password(password: &Password, kdf_params: &KdfParams) -> User {
+        let salt = Salt::with_length(kdf_params.salt_length).unwrap();
+
+        User {
+            id: 999,
+            preferences: Preferences::default(),
+            credentials: Credentials {
+                email: Email::try_from("username@server.domain").unwrap(),
+                password: PasswordHash::with_salt(password, &salt, kdf_params).unwrap(),
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn verify_password_with_correct_password_should_succeed() {
+        let password = Password::try_from("abcABC123&".to_string()).unwrap();
+        let mut user = user_with_password(&password, &KdfParams::default());
+
+        let user_app = new_user_application();
+        let verified = user_app.verify_password(&mut user, &password).await.unwrap();
+        assert!(verified);
+    }
+
+    #[tokio::test]
+    async fn verify_password_with_wrong_password_should_report_unverified() {
+        let password = Password::try_from("abcABC123&".to_string()).unwrap();
+        let wrong = Password::try_from("wrongABC123&".to_string()).unwrap();
+        let mut user = user_with_password(&password, &KdfParams::default());
+
+        let user_app = new_user_application();
+        let verified = user_app.verify_password(&mut user, &wrong).await.unwrap();
+        assert!(!verified);
+    }
+
+    #[tokio::test]
+    async fn verify_password_should_rehash_under_weaker_parameters() {
+        let password = Password::try_from("abcABC123&".to_string()).unwrap();
+        let weak_params = KdfParams {
+            memory_cost: 1024,
+            time_cost: 1,
+            parallelism: 1,
+            salt_length: 16,
+        };
+
+        let mut user = user_with_password(&password, &weak_params);
+
+        let saved = Arc::new(AtomicBool::new(false));
+        let saved_flag = saved.clone();
+
+        let mut user_repo = UserRepositoryMock::default();
+        user_repo.save_fn = Some(move |_: &UserRepositoryMock, _: &mut User| {
+            saved_flag.store(true, Ordering::SeqCst);
+            Ok(())
+        });
+
+        let mut user_app = new_user_application();
+        user_app.user_repo = Arc::new(user_repo);
+
+        let verified = user_app.verify_password(&mut user, &password).await.unwrap();
+        assert!(verified);
+        assert!(saved.load(Ordering::SeqCst));
+    }
+}
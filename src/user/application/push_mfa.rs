@@ -0,0 +1,269 @@
+use super::{EventService, MailService, PushService, UserApplication, UserRepository};
+use crate::cache::Cache;
+use crate::crypto;
+use crate::mfa::service::MfaService;
+use crate::secret::application::SecretRepository;
+use crate::token::domain::{Claims, Token, TokenKind};
+use crate::token::service::TokenService;
+use crate::user::error::{Error, Result};
+use std::time::Duration;
+
+const APPROVAL_ID_LEN: usize = 32;
+const APPROVAL_TIMEOUT: Duration = Duration::from_secs(120); // 2 minutes
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum ApprovalStatus {
+    Pending,
+    Approved,
+    Denied,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct LoginApprovalContext {
+    pub(crate) ip: String,
+    pub(crate) client_id: String,
+    pub(crate) requested_at: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+struct LoginApproval {
+    request_id: String,
+    user_id: i32,
+    context: LoginApprovalContext,
+    status: ApprovalStatus,
+}
+
+fn approval_key(request_id: &str) -> String {
+    format!("mfa_push_approval::{}", request_id)
+}
+
+/// Mirrors `session::application::push_login_key`: a short-lived proof, keyed by user id rather
+/// than `request_id`, that `login`'s own `Some(MfaMethod::Push)` branch consumes directly off
+/// the `Cache` once a pending approval has been resolved — the same bridge `webauthn.rs` leaves
+/// for its assertion ceremony.
+pub(crate) fn push_login_key(user_id: i32) -> String {
+    format!("push_login::{}", user_id)
+}
+
+pub enum PushApprovalResult {
+    Pending,
+    Denied,
+    Expired,
+    Granted(Claims),
+}
+
+impl<U, S, T, F, M, B, C, P> UserApplication<U, S, T, F, M, B, C, P>
+where
+    U: UserRepository,
+    S: SecretRepository,
+    T: TokenService,
+    F: MfaService,
+    M: MailService,
+    B: EventService,
+    C: Cache,
+    P: PushService,
+{
+    /// Registers `push_token` as the device to notify for the future push-MFA challenges of the
+    /// user behind `session`, replacing any previously registered token.
+    #[instrument(skip(self, session, push_token))]
+    pub async fn register_push_device(&self, session: Token, push_token: &str) -> Result<()> {
+        let claims = self.token_srv.claims(session).await?;
+        if !claims.payload().kind().is_session() {
+            return Err(Error::WrongToken);
+        }
+
+        let user_id: i32 = claims.payload().subject().parse().map_err(|_| Error::WrongToken)?;
+        let mut user = self.user_repo.find(user_id).await?;
+        user.preferences.push_token = Some(push_token.to_string());
+        self.user_repo.save(&user).await
+    }
+
+    /// Starts a push-based MFA challenge for `user_id`: caches a Pending approval bound to the
+    /// login context (`ip`, `client_id`), and notifies the user's registered device via
+    /// `PushService`. The returned request id is what the originating login polls with.
+    #[instrument(skip(self))]
+    pub async fn request_mfa_approval(&self, user_id: i32, ip: &str, client_id: &str) -> Result<String> {
+        let user = self.user_repo.find(user_id).await?;
+        let push_token = user.preferences.push_token.as_ref().ok_or(Error::MfaRequired)?;
+
+        let request_id = crypto::get_random_string(APPROVAL_ID_LEN);
+        let entry = LoginApproval {
+            request_id: request_id.clone(),
+            user_id,
+            context: LoginApprovalContext {
+                ip: ip.to_string(),
+                client_id: client_id.to_string(),
+                requested_at: crate::time::unix_timestamp(std::time::SystemTime::now()),
+            },
+            status: ApprovalStatus::Pending,
+        };
+
+        self.cache
+            .save(&approval_key(&request_id), &entry, APPROVAL_TIMEOUT)
+            .await?;
+
+        self.push_srv
+            .send_login_approval(push_token, &entry.context)
+            .await?;
+
+        Ok(request_id)
+    }
+
+    /// Polled by the originating login attempt. Reports the current status of a push-MFA
+    /// approval: `Granted` with a fresh session once the device has approved it, `Denied` if
+    /// rejected, and `Expired` once the cache entry has been evicted or already consumed.
+    #[instrument(skip(self))]
+    pub async fn poll_mfa_approval(&self, request_id: &str) -> Result<PushApprovalResult> {
+        let key = approval_key(request_id);
+        let entry: LoginApproval = match self.cache.find(&key).await {
+            Ok(entry) => entry,
+            Err(_) => return Ok(PushApprovalResult::Expired),
+        };
+
+        if !crypto::constant_time_eq(request_id.as_bytes(), entry.request_id.as_bytes()) {
+            return Ok(PushApprovalResult::Expired);
+        }
+
+        match entry.status {
+            ApprovalStatus::Pending => Ok(PushApprovalResult::Pending),
+            ApprovalStatus::Denied => {
+                self.cache.delete(&key).await?;
+                Ok(PushApprovalResult::Denied)
+            }
+            ApprovalStatus::Approved => {
+                self.cache.delete(&key).await?;
+                let claims = self
+                    .token_srv
+                    .issue(TokenKind::Session, &entry.user_id.to_string())
+                    .await?;
+
+                Ok(PushApprovalResult::Granted(claims))
+            }
+        }
+    }
+
+    /// Called from the user's device to approve or deny a pending push-MFA challenge identified
+    /// by `request_id`, which is matched against the cached entry in constant time. Approval also
+    /// leaves a short-lived proof under `push_login_key`, so a retried `login` call for a user on
+    /// `Some(MfaMethod::Push)` can pick up the outcome without itself knowing `request_id`.
+    #[instrument(skip(self))]
+    pub async fn resolve_mfa_approval(&self, request_id: &str, approve: bool) -> Result<()> {
+        let key = approval_key(request_id);
+        let mut entry: LoginApproval = self.cache.find(&key).await.map_err(|_| Error::NotFound)?;
+
+        if !crypto::constant_time_eq(request_id.as_bytes(), entry.request_id.as_bytes()) {
+            return Err(Error::NotFound);
+        }
+
+        if entry.status != ApprovalStatus::Pending {
+            return Err(Error::WrongToken);
+        }
+
+        entry.status = if approve {
+            ApprovalStatus::Approved
+        } else {
+            ApprovalStatus::Denied
+        };
+
+        self.cache.save(&key, &entry, APPROVAL_TIMEOUT).await?;
+
+        if approve {
+            self.cache
+                .save(&push_login_key(entry.user_id), &true, APPROVAL_TIMEOUT)
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{push_login_key, PushApprovalResult};
+    use crate::cache::Cache;
+    use crate::user::application::test::{new_user_application, PushServiceMock, UserRepositoryMock};
+    use crate::user::domain::{Credentials, Email, Password, PasswordHash, Preferences, Salt, User};
+    use crate::user::error::Error;
+    use std::sync::Arc;
+
+    fn accepting_push_srv() -> PushServiceMock {
+        let mut push_srv = PushServiceMock::default();
+        push_srv.send_login_approval_fn = Some(|_: &PushServiceMock, _: &str, _: &super::LoginApprovalContext| Ok(()));
+        push_srv
+    }
+
+    fn plain_user() -> User {
+        let password = Password::try_from("abcABC123&".to_string()).unwrap();
+        let salt = Salt::with_length(32).unwrap();
+
+        User {
+            id: 999,
+            preferences: Preferences::default(),
+            credentials: Credentials {
+                email: Email::try_from("username@server.domain").unwrap(),
+                password: PasswordHash::with_salt(&password, &salt, &Default::default()).unwrap(),
+            },
+        }
+    }
+
+    fn user_with_push_token() -> User {
+        let mut user = plain_user();
+        user.preferences.push_token = Some("device-token".to_string());
+        user
+    }
+
+    #[tokio::test]
+    async fn request_mfa_approval_without_a_registered_device_should_fail() {
+        let mut user_repo = UserRepositoryMock::default();
+        user_repo.find_fn = Some(|_: &UserRepositoryMock, _: i32| Ok(plain_user()));
+
+        let mut user_app = new_user_application();
+        user_app.user_repo = Arc::new(user_repo);
+
+        let result = user_app.request_mfa_approval(999, "127.0.0.1", "client").await;
+        assert!(matches!(result, Err(Error::MfaRequired)), "{:?}", result);
+    }
+
+    #[tokio::test]
+    async fn poll_mfa_approval_for_unknown_request_should_report_expired() {
+        let user_app = new_user_application();
+        let result = user_app.poll_mfa_approval("not-a-real-request").await.unwrap();
+        assert!(matches!(result, PushApprovalResult::Expired));
+    }
+
+    #[tokio::test]
+    async fn resolve_then_poll_mfa_approval_should_grant_a_session() {
+        let mut user_repo = UserRepositoryMock::default();
+        user_repo.find_fn = Some(|_: &UserRepositoryMock, _: i32| Ok(user_with_push_token()));
+
+        let mut user_app = new_user_application();
+        user_app.user_repo = Arc::new(user_repo);
+        user_app.push_srv = Arc::new(accepting_push_srv());
+
+        let request_id = user_app.request_mfa_approval(999, "127.0.0.1", "client").await.unwrap();
+        user_app.resolve_mfa_approval(&request_id, true).await.unwrap();
+
+        let result = user_app.poll_mfa_approval(&request_id).await.unwrap();
+        assert!(matches!(result, PushApprovalResult::Granted(_)));
+
+        // the bridge login_unthrottled consumes is left behind too.
+        let bridged: bool = user_app.cache.find(&push_login_key(999)).await.unwrap();
+        assert!(bridged);
+    }
+
+    #[tokio::test]
+    async fn resolve_mfa_approval_denied_should_be_reported_as_denied() {
+        let mut user_repo = UserRepositoryMock::default();
+        user_repo.find_fn = Some(|_: &UserRepositoryMock, _: i32| Ok(user_with_push_token()));
+
+        let mut user_app = new_user_application();
+        user_app.user_repo = Arc::new(user_repo);
+        user_app.push_srv = Arc::new(accepting_push_srv());
+
+        let request_id = user_app.request_mfa_approval(999, "127.0.0.1", "client").await.unwrap();
+        user_app.resolve_mfa_approval(&request_id, false).await.unwrap();
+
+        let result = user_app.poll_mfa_approval(&request_id).await.unwrap();
+        assert!(matches!(result, PushApprovalResult::Denied));
+    }
+}
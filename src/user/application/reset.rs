@@ -0,0 +1,133 @@
+use super::outbox::{OutboxEvent, OutboxRepository};
+use super::{EventService, MailService, PushService, UserApplication, UserRepository};
+use crate::cache::Cache;
+use crate::mfa::service::MfaService;
+use crate::secret::application::SecretRepository;
+use crate::token::domain::{Token, TokenKind};
+use crate::token::service::TokenService;
+use crate::user::domain::{Email, Password, PasswordHash, Salt};
+use crate::user::error::{Error, Result};
+
+impl<U, S, T, F, M, B, C, P> UserApplication<U, S, T, F, M, B, C, P>
+where
+    U: UserRepository + OutboxRepository,
+    S: SecretRepository,
+    T: TokenService,
+    F: MfaService,
+    M: MailService,
+    B: EventService,
+    C: Cache,
+    P: PushService,
+{
+    /// Issues a `TokenKind::Reset` token scoped to the user behind `email` and sends it via
+    /// `MailService::send_password_reset_email`. To avoid account enumeration this always
+    /// returns `Ok(())`, even when no user exists for `email` — it simply skips the email send
+    /// in that case.
+    #[instrument(skip(self))]
+    pub async fn request_password_reset(&self, email: Email) -> Result<()> {
+        let Ok(user) = self.user_repo.find_by_email(&email).await else {
+            return Ok(());
+        };
+
+        let claims = self
+            .token_srv
+            .issue(TokenKind::Reset, &user.id.to_string())
+            .await?;
+
+        self.mail_srv
+            .send_password_reset_email(&user.credentials.email, claims.token())?;
+
+        Ok(())
+    }
+
+    /// Validates a reset token, re-hashes `new_password` with a fresh salt, persists it, revokes
+    /// the reset token so it cannot be reused, and enqueues `UserPasswordChanged` for
+    /// `dispatch_outbox` to deliver, rather than emitting it inline and risking the event being
+    /// lost to a transient `EventService` failure.
+    #[instrument(skip(self, token, new_password))]
+    pub async fn reset_password_with_token(&self, token: Token, new_password: Password) -> Result<()> {
+        let claims = self.token_srv.claims(token).await?;
+        if !claims.payload().kind().is_reset() {
+            return Err(Error::WrongToken);
+        }
+
+        let user_id = claims
+            .payload()
+            .subject()
+            .parse()
+            .map_err(|_| Error::WrongToken)?;
+
+        let mut user = self.user_repo.find(user_id).await?;
+
+        let salt = Salt::with_length(self.hash_length)?;
+        user.credentials.password = PasswordHash::with_salt(&new_password, &salt, &self.kdf_params)?;
+
+        self.user_repo.save(&user).await?;
+        self.token_srv.revoke(&claims).await?;
+        self.user_repo.enqueue(user.id, OutboxEvent::UserPasswordChanged).await
+    }
+}
+
+// `reset_password_with_token` is not covered here: it requires `U: UserRepository +
+// OutboxRepository`, and `UserRepositoryMock` (defined outside this tree, in the module's
+// missing `mod.rs`) has no visible `OutboxRepository` impl to exercise that path against.
+#[cfg(test)]
+mod test {
+    use crate::token::domain::{Claims, Payload, Token, TokenKind};
+    use crate::token::service::test::TokenServiceMock;
+    use crate::user::application::test::{new_user_application, MailServiceMock, UserRepositoryMock};
+    use crate::user::domain::{Credentials, Email, Password, PasswordHash, Preferences, Salt, User};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn plain_user() -> User {
+        let password = Password::try_from("abcABC123&".to_string()).unwrap();
+        let salt = Salt::with_length(32).unwrap();
+
+        User {
+            id: 999,
+            preferences: Preferences::default(),
+            credentials: Credentials {
+                email: Email::try_from("username@server.domain").unwrap(),
+                password: PasswordHash::with_salt(&password, &salt, &Default::default()).unwrap(),
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn request_password_reset_for_unknown_email_should_still_report_ok() {
+        let mut user_repo = UserRepositoryMock::default();
+        user_repo.find_by_email_fn = Some(|_: &UserRepositoryMock, _: &Email| Err(crate::user::error::Error::NotFound));
+
+        let mut user_app = new_user_application();
+        user_app.user_repo = Arc::new(user_repo);
+
+        let email = Email::try_from("username@server.domain").unwrap();
+        user_app.request_password_reset(email).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn request_password_reset_must_not_fail() {
+        let mut user_repo = UserRepositoryMock::default();
+        user_repo.find_by_email_fn = Some(|_: &UserRepositoryMock, _: &Email| Ok(plain_user()));
+
+        let mut token_srv = TokenServiceMock::default();
+        token_srv.issue_fn = Some(|_: &TokenServiceMock, kind: TokenKind, sub: &str| {
+            Ok(Claims {
+                token: "abc.abc.abc".to_string().try_into().unwrap(),
+                payload: Payload::new(kind, Duration::from_secs(60)).with_subject(sub),
+            })
+        });
+
+        let mut mail_srv = MailServiceMock::default();
+        mail_srv.send_password_reset_email_fn = Some(|_: &MailServiceMock, _: &Email, _: &Token| Ok(()));
+
+        let mut user_app = new_user_application();
+        user_app.user_repo = Arc::new(user_repo);
+        user_app.token_srv = Arc::new(token_srv);
+        user_app.mail_srv = Arc::new(mail_srv);
+
+        let email = Email::try_from("username@server.domain").unwrap();
+        user_app.request_password_reset(email).await.unwrap();
+    }
+}
@@ -1,12 +1,15 @@
-use super::{EventService, MailService, UserApplication, UserRepository};
+use super::invite::InviteGrant;
+use super::outbox::OutboxEvent;
+use super::{EventService, MailService, PushService, UserApplication, UserRepository};
 use crate::cache::Cache;
 use crate::mfa::service::MfaService;
+use crate::session::scope::ScopeSet;
 use crate::token::domain::{Claims, Token, TokenKind};
 use crate::token::service::TokenService;
-use crate::user::domain::{Credentials, Email, Password, PasswordHash, Salt, User};
+use crate::user::domain::{Credentials, Email, Password, PasswordHash, Preferences, Salt, User};
 use crate::user::error::{Error, Result};
 
-impl<U, S, T, F, M, B, C> UserApplication<U, S, T, F, M, B, C>
+impl<U, S, T, F, M, B, C, P> UserApplication<U, S, T, F, M, B, C, P>
 where
     U: UserRepository,
     T: TokenService,
@@ -14,6 +17,7 @@ where
     M: MailService,
     B: EventService,
     C: Cache,
+    P: PushService,
 {
     /// Stores the given credentials in the cache and sends an email with the token to be
     /// passed as parameter to the signup_with_token method.
@@ -30,7 +34,7 @@ where
         let salt = Salt::with_length(self.hash_length)?;
         let credentials = Credentials {
             email,
-            password: PasswordHash::with_salt(&password, &salt)?,
+            password: PasswordHash::with_salt(&password, &salt, &self.kdf_params)?,
         };
 
         let key = credentials.hash();
@@ -49,20 +53,42 @@ where
         Ok(())
     }
 
-    /// Given a valid verification token, performs the signup of the corresponding user.
-    #[instrument(skip(self))]
-    pub async fn signup_with_token(&self, token: Token) -> Result<Claims> {
+    /// Given a valid verification token, performs the signup of the corresponding user. Also
+    /// accepts an invite token, in which case `password` completes the credentials and the new
+    /// user's email is bound to the one the invite was issued for, not to anything the caller
+    /// supplies.
+    #[instrument(skip(self, password))]
+    pub async fn signup_with_token(&self, token: Token, password: Option<Password>) -> Result<Claims> {
         let claims = self.token_srv.claims(token).await?;
 
-        if !claims.payload().kind().is_verification() {
-            return Error::WrongToken.into();
-        }
-
-        let mut user = self
-            .cache
-            .find(claims.payload().subject())
-            .await
-            .map(Credentials::into)?;
+        let mut user = match claims.payload().kind() {
+            TokenKind::Verification => self
+                .cache
+                .find(claims.payload().subject())
+                .await
+                .map(Credentials::into)?,
+
+            TokenKind::Invite => {
+                let key = super::invite::invite_key(claims.payload().subject());
+                let grant: InviteGrant = self.cache.find(&key).await.map_err(|_| Error::WrongToken)?;
+                let password = password.ok_or(Error::WrongCredentials)?;
+                let salt = Salt::with_length(self.hash_length)?;
+
+                self.cache.delete(&key).await?;
+
+                User {
+                    id: 0,
+                    credentials: Credentials {
+                        email: grant.email,
+                        password: PasswordHash::with_salt(&password, &salt, &self.kdf_params)?,
+                    },
+                    preferences: Preferences::default(),
+                    granted_scopes: ScopeSet::parse(&grant.scope),
+                }
+            }
+
+            _ => return Error::WrongToken.into(),
+        };
 
         self.token_srv.revoke(&claims).await?;
         self.signup(&mut user).await
@@ -71,9 +97,7 @@ where
     /// Performs the signup for the given user.
     #[instrument(skip(self))]
     pub async fn signup(&self, user: &mut User) -> Result<Claims> {
-        self.user_repo.create(user).await?;
-        // TODO: implement outbox pattern for events publishment
-        self.event_srv.emit_user_created(user).await?;
+        self.user_repo.create(user, &OutboxEvent::UserCreated).await?;
 
         self.token_srv
             .issue(TokenKind::Session, &user.id.to_string())
@@ -84,6 +108,8 @@ where
 
 #[cfg(test)]
 mod test {
+    use super::super::outbox::OutboxEvent;
+    use super::super::password::KdfParams;
     use crate::{
         cache::Cache,
         token::{
@@ -91,9 +117,7 @@ mod test {
             service::test::TokenServiceMock,
         },
         user::{
-            application::test::{
-                new_user_application, EventServiceMock, MailServiceMock, UserRepositoryMock,
-            },
+            application::test::{new_user_application, MailServiceMock, UserRepositoryMock},
             domain::{Credentials, Email, Password, PasswordHash, Preferences, Salt, User},
             error::Error,
         },
@@ -115,7 +139,7 @@ mod test {
                 preferences: Preferences::default(),
                 credentials: Credentials {
                     email: email.clone(),
-                    password: PasswordHash::with_salt(&password, &salt).unwrap(),
+                    password: PasswordHash::with_salt(&password, &salt, &KdfParams::default()).unwrap(),
                 },
             })
         });
@@ -188,23 +212,18 @@ mod test {
     #[tokio::test]
     async fn signup_with_token_must_not_fail() {
         let mut user_repo = UserRepositoryMock::default();
-        user_repo.create_fn = Some(|_: &UserRepositoryMock, user: &mut User| {
+        user_repo.create_fn = Some(|_: &UserRepositoryMock, user: &mut User, event: &OutboxEvent| {
             assert_eq!(
                 user.credentials.email.as_ref(),
                 "username@server.domain",
                 "unexpected email"
             );
+            assert_eq!(*event, OutboxEvent::UserCreated, "unexpected outbox event");
 
             user.id = 999;
             Ok(())
         });
 
-        let mut event_srv: EventServiceMock = Default::default();
-        event_srv.emit_user_created_fn = Some(|_: &EventServiceMock, user: &User| {
-            assert_eq!(user.id, 999, "unexpected user id");
-            Ok(())
-        });
-
         let mut token_srv = TokenServiceMock::default();
         token_srv.issue_fn = Some(|_: &TokenServiceMock, kind: TokenKind, sub: &str| {
             Ok(Claims {
@@ -241,7 +260,7 @@ mod test {
         let salt = Salt::with_length(32).unwrap();
         let credentials = Credentials {
             email: Email::try_from("username@server.domain").unwrap(),
-            password: PasswordHash::with_salt(&password, &salt).unwrap(),
+            password: PasswordHash::with_salt(&password, &salt, &KdfParams::default()).unwrap(),
         };
 
         let mut user_app = new_user_application();
@@ -254,10 +273,9 @@ mod test {
         user_app.hash_length = 32;
         user_app.user_repo = Arc::new(user_repo);
         user_app.token_srv = Arc::new(token_srv);
-        user_app.event_srv = Arc::new(event_srv);
 
         let token = Token::try_from("abc.abc.abc".to_string()).unwrap();
-        let token = user_app.signup_with_token(token).await.unwrap();
+        let token = user_app.signup_with_token(token, None).await.unwrap();
 
         assert_eq!(
             token.payload.kind(),
@@ -294,7 +312,7 @@ mod test {
         let salt = Salt::with_length(32).unwrap();
         let credentials = Credentials {
             email: Email::try_from("username@server.domain").unwrap(),
-            password: PasswordHash::with_salt(&password, &salt).unwrap(),
+            password: PasswordHash::with_salt(&password, &salt, &KdfParams::default()).unwrap(),
         };
 
         let mut user_app = new_user_application();
@@ -308,7 +326,7 @@ mod test {
         user_app.token_srv = Arc::new(token_srv);
 
         let token = Token::try_from("abc.abc.abc".to_string()).unwrap();
-        let result = user_app.signup_with_token(token).await;
+        let result = user_app.signup_with_token(token, None).await;
         assert!(
             matches!(result, Err(Error::WrongToken)),
             "got result = {:?}, want error = {}",
@@ -318,15 +336,116 @@ mod test {
     }
 
     #[tokio::test]
-    async fn signup_must_not_fail() {
+    async fn signup_with_token_accepts_invite_token() {
         let mut user_repo = UserRepositoryMock::default();
-        user_repo.create_fn = Some(|_: &UserRepositoryMock, user: &mut User| {
+        user_repo.create_fn = Some(|_: &UserRepositoryMock, user: &mut User, event: &OutboxEvent| {
+            assert_eq!(
+                user.credentials.email.as_ref(),
+                "invitee@server.domain",
+                "new user's email must be bound to the invite, not caller-supplied"
+            );
+            assert_eq!(*event, OutboxEvent::UserCreated, "unexpected outbox event");
+
             user.id = 999;
             Ok(())
         });
 
-        let mut event_srv: EventServiceMock = Default::default();
-        event_srv.emit_user_created_fn = Some(|_: &EventServiceMock, _: &User| Ok(()));
+        let mut token_srv = TokenServiceMock::default();
+        token_srv.issue_fn = Some(|_: &TokenServiceMock, kind: TokenKind, sub: &str| {
+            Ok(Claims {
+                token: "123.123.123".to_string().try_into().unwrap(),
+                payload: Payload::new(kind, Duration::from_secs(60)).with_subject(sub),
+            })
+        });
+
+        token_srv.claims_fn = Some(|_: &TokenServiceMock, token: Token| {
+            assert_eq!(token.as_ref(), "abc.abc.abc", "unexpected token");
+            Ok(Claims {
+                token,
+                payload: Payload::new(TokenKind::Invite, Duration::from_secs(60)).with_subject("invite-key"),
+            })
+        });
+
+        token_srv.revoke_fn = Some(|_: &TokenServiceMock, _: &Claims| Ok(()));
+
+        let grant = super::super::invite::InviteGrant {
+            email: Email::try_from("invitee@server.domain").unwrap(),
+            scope: "read write".to_string(),
+        };
+
+        let mut user_app = new_user_application();
+        user_app
+            .cache
+            .save(
+                &super::super::invite::invite_key("invite-key"),
+                grant,
+                Duration::from_secs(60),
+            )
+            .await
+            .unwrap();
+
+        user_app.hash_length = 32;
+        user_app.user_repo = Arc::new(user_repo);
+        user_app.token_srv = Arc::new(token_srv);
+
+        let token = Token::try_from("abc.abc.abc".to_string()).unwrap();
+        let password = Password::try_from("abcABC123&".to_string()).unwrap();
+        let claims = user_app.signup_with_token(token, Some(password)).await.unwrap();
+
+        assert_eq!(
+            claims.payload.subject(),
+            "999",
+            "expected user id in token subject"
+        );
+    }
+
+    #[tokio::test]
+    async fn signup_with_invite_token_without_password_must_fail() {
+        let mut token_srv = TokenServiceMock::default();
+        token_srv.claims_fn = Some(|_: &TokenServiceMock, token: Token| {
+            Ok(Claims {
+                token,
+                payload: Payload::new(TokenKind::Invite, Duration::from_secs(60)).with_subject("invite-key"),
+            })
+        });
+
+        let grant = super::super::invite::InviteGrant {
+            email: Email::try_from("invitee@server.domain").unwrap(),
+            scope: "read write".to_string(),
+        };
+
+        let mut user_app = new_user_application();
+        user_app
+            .cache
+            .save(
+                &super::super::invite::invite_key("invite-key"),
+                grant,
+                Duration::from_secs(60),
+            )
+            .await
+            .unwrap();
+
+        user_app.hash_length = 32;
+        user_app.token_srv = Arc::new(token_srv);
+
+        let token = Token::try_from("abc.abc.abc".to_string()).unwrap();
+        let result = user_app.signup_with_token(token, None).await;
+        assert!(
+            matches!(result, Err(Error::WrongCredentials)),
+            "got result = {:?}, want error = {}",
+            result,
+            Error::WrongCredentials
+        );
+    }
+
+    #[tokio::test]
+    async fn signup_must_not_fail() {
+        let mut user_repo = UserRepositoryMock::default();
+        user_repo.create_fn = Some(|_: &UserRepositoryMock, user: &mut User, event: &OutboxEvent| {
+            assert_eq!(*event, OutboxEvent::UserCreated, "unexpected outbox event");
+            user.id = 999;
+            Ok(())
+        });
 
         let mut token_srv = TokenServiceMock::default();
         token_srv.issue_fn = Some(|_: &TokenServiceMock, kind: TokenKind, sub: &str| {
@@ -343,14 +462,13 @@ mod test {
         user_app.hash_length = 32;
         user_app.user_repo = Arc::new(user_repo);
         user_app.token_srv = Arc::new(token_srv);
-        user_app.event_srv = Arc::new(event_srv);
 
         let email = Email::try_from("username@server.domain").unwrap();
         let password = Password::try_from("abcABC123&".to_string()).unwrap();
         let salt = Salt::with_length(32).unwrap();
         let credentials = Credentials {
             email,
-            password: PasswordHash::with_salt(&password, &salt).unwrap(),
+            password: PasswordHash::with_salt(&password, &salt, &KdfParams::default()).unwrap(),
         };
 
         let mut user = User {
@@ -0,0 +1,338 @@
+use super::{EventService, MailService, PushService, UserApplication, UserRepository};
+use crate::cache::Cache;
+use crate::crypto;
+use crate::mfa::service::MfaService;
+use crate::secret::application::SecretRepository;
+use crate::secret::domain::{Secret, SecretKind};
+use crate::token::domain::Token;
+use crate::token::service::TokenService;
+use crate::user::domain::{Password, PasswordHash, Salt};
+use crate::user::error::{Error, Result};
+use std::time::Duration;
+
+const TOTP_SECRET_LEN: usize = 20; // 160 bits, RFC 6238 recommends at least 128
+const TOTP_ISSUER: &str = "alvidir/oauth";
+const PENDING_TOTP_TIMEOUT: Duration = Duration::from_secs(600); // 10 minutes to confirm enrollment
+
+const RECOVERY_CODE_COUNT: usize = 10;
+const RECOVERY_CODE_LEN: usize = 10;
+
+const BASE32_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+fn base32_encode(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity((data.len() * 8).div_ceil(5));
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+
+    for &byte in data {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+
+    if bits > 0 {
+        encoded.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+
+    encoded
+}
+
+fn pending_totp_key(user_id: i32) -> String {
+    format!("totp_enroll::{}", user_id)
+}
+
+#[derive(Serialize, Deserialize)]
+struct PendingTotp {
+    secret: Vec<u8>,
+}
+
+/// Returned once, at enrollment time: `provisioning_uri` is what the user scans into their
+/// authenticator app, `recovery_codes` are shown once and cannot be recovered afterwards.
+pub struct TotpEnrollment {
+    pub provisioning_uri: String,
+    pub recovery_codes: Vec<String>,
+}
+
+impl<U, S, T, F, M, B, C, P> UserApplication<U, S, T, F, M, B, C, P>
+where
+    U: UserRepository,
+    S: SecretRepository,
+    T: TokenService,
+    F: MfaService,
+    M: MailService,
+    B: EventService,
+    C: Cache,
+    P: PushService,
+{
+    /// Starts TOTP enrollment for the user behind `session`: generates a random RFC 6238 secret
+    /// and holds it in the cache until `confirm_totp` proves the user's authenticator app was
+    /// seeded with it. The secret is not persisted, and logins do not require a code, until that
+    /// confirmation succeeds. Recovery codes are not generated here: they are only ever worth
+    /// having once the user has actually proven possession of the secret, so `confirm_totp` is
+    /// what generates them.
+    #[instrument(skip(self, session))]
+    pub async fn enroll_totp(&self, session: Token) -> Result<TotpEnrollment> {
+        let claims = self.token_srv.claims(session).await?;
+        if !claims.payload().kind().is_session() {
+            return Err(Error::WrongToken);
+        }
+
+        let user_id: i32 = claims.payload().subject().parse().map_err(|_| Error::WrongToken)?;
+        let user = self.user_repo.find(user_id).await?;
+
+        let secret = crypto::get_random_string(TOTP_SECRET_LEN).into_bytes();
+        self.cache
+            .save(
+                &pending_totp_key(user_id),
+                &PendingTotp { secret: secret.clone() },
+                PENDING_TOTP_TIMEOUT,
+            )
+            .await?;
+
+        Ok(TotpEnrollment {
+            provisioning_uri: format!(
+                "otpauth://totp/{issuer}:{email}?secret={secret}&issuer={issuer}&digits=6&period=30",
+                issuer = TOTP_ISSUER,
+                email = user.credentials.email.as_ref(),
+                secret = base32_encode(&secret),
+            ),
+            recovery_codes: Vec::new(),
+        })
+    }
+
+    /// Activates a pending TOTP enrollment once `code` proves the user's authenticator is
+    /// correctly seeded, persisting the secret as the user's `SecretKind::Totp` record so future
+    /// logins require it, and (re-)generating the user's recovery codes now that possession of
+    /// the secret is actually proven. Abandoning enrollment after `enroll_totp` and starting over
+    /// never stacks duplicate recovery-code rows, since those are only ever written here.
+    #[instrument(skip(self, session, code))]
+    pub async fn confirm_totp(&self, session: Token, code: &str) -> Result<Vec<String>> {
+        let claims = self.token_srv.claims(session).await?;
+        if !claims.payload().kind().is_session() {
+            return Err(Error::WrongToken);
+        }
+
+        let user_id: i32 = claims.payload().subject().parse().map_err(|_| Error::WrongToken)?;
+        let key = pending_totp_key(user_id);
+        let pending: PendingTotp = self.cache.find(&key).await.map_err(|_| Error::WrongToken)?;
+
+        crypto::verify_totp(&pending.secret, code).map_err(|_| Error::WrongCredentials)?;
+
+        let mut secret = Secret::new(user_id, SecretKind::Totp, pending.secret);
+        self.secret_repo.create(&mut secret).await?;
+        self.cache.delete(&key).await?;
+
+        self.generate_recovery_codes(user_id).await
+    }
+
+    /// Disables TOTP for the user behind `session`, deleting its stored secret so future logins
+    /// stop requiring a code.
+    #[instrument(skip(self, session))]
+    pub async fn disable_totp(&self, session: Token) -> Result<()> {
+        let claims = self.token_srv.claims(session).await?;
+        if !claims.payload().kind().is_session() {
+            return Err(Error::WrongToken);
+        }
+
+        let user_id: i32 = claims.payload().subject().parse().map_err(|_| Error::WrongToken)?;
+        let secret = self
+            .secret_repo
+            .find_by_owner_and_kind(user_id, SecretKind::Totp)
+            .await?;
+
+        self.secret_repo.delete(&secret).await
+    }
+
+    /// Replaces (rather than stacks) the user's `SecretKind::Recovery` row: `consume_recovery_code`
+    /// assumes a single current row per user, so a re-enrollment must overwrite the previous
+    /// batch of codes instead of inserting a second one alongside it.
+    async fn generate_recovery_codes(&self, user_id: i32) -> Result<Vec<String>> {
+        let mut plaintext = Vec::with_capacity(RECOVERY_CODE_COUNT);
+        let mut hashed = Vec::with_capacity(RECOVERY_CODE_COUNT);
+
+        for _ in 0..RECOVERY_CODE_COUNT {
+            let code = generate_recovery_code();
+            let salt = Salt::with_length(self.hash_length)?;
+            hashed.push(
+                PasswordHash::with_salt(&code, &salt, &self.kdf_params)?
+                    .as_ref()
+                    .to_string(),
+            );
+            plaintext.push(code.as_ref().to_string());
+        }
+
+        let data = hashed.join("\n").into_bytes();
+        let exists = self
+            .secret_repo
+            .find_by_owner_and_kind(user_id, SecretKind::Recovery)
+            .await
+            .is_ok();
+
+        if exists {
+            self.secret_repo.save(&Secret::new(user_id, SecretKind::Recovery, data)).await?;
+        } else {
+            self.secret_repo
+                .create(&mut Secret::new(user_id, SecretKind::Recovery, data))
+                .await?;
+        }
+
+        Ok(plaintext)
+    }
+
+    /// Consumes a one-time recovery code for `user_id`, bypassing TOTP. Returns whether `code`
+    /// matched a still-unused recovery code; a matched code is removed so it cannot be replayed.
+    #[instrument(skip(self, code))]
+    pub async fn consume_recovery_code(&self, user_id: i32, code: &str) -> Result<bool> {
+        let Ok(candidate) = Password::try_from(code.to_string()) else {
+            return Ok(false);
+        };
+
+        let Ok(secret) = self.secret_repo.find_by_owner_and_kind(user_id, SecretKind::Recovery).await else {
+            return Ok(false);
+        };
+
+        let data = String::from_utf8_lossy(secret.data()).into_owned();
+        let mut remaining: Vec<&str> = data.lines().collect();
+
+        let Some(pos) = remaining.iter().position(|hash| {
+            PasswordHash::try_from(hash.to_string()).is_ok_and(|hash| hash.verify(&candidate))
+        }) else {
+            return Ok(false);
+        };
+
+        remaining.remove(pos);
+        let updated = Secret::new(user_id, SecretKind::Recovery, remaining.join("\n").into_bytes());
+        self.secret_repo.save(&updated).await?;
+
+        Ok(true)
+    }
+}
+
+fn generate_recovery_code() -> Password {
+    let code = format!(
+        "{}{}&",
+        crypto::get_random_string(RECOVERY_CODE_LEN / 2).to_lowercase(),
+        crypto::get_random_string(RECOVERY_CODE_LEN / 2).to_uppercase(),
+    );
+
+    code.try_into().expect("generated recovery code must satisfy Password's invariants")
+}
+
+#[cfg(test)]
+mod test {
+    use super::{pending_totp_key, PendingTotp, RECOVERY_CODE_COUNT};
+    use crate::cache::Cache;
+    use crate::crypto;
+    use crate::result::Error as RepoError;
+    use crate::secret::application::tests::SecretRepositoryMock;
+    use crate::secret::domain::Secret;
+    use crate::token::domain::{Claims, Payload, Token, TokenKind};
+    use crate::token::service::test::TokenServiceMock;
+    use crate::user::application::test::new_user_application;
+    use crate::user::error::Error;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    fn session_token() -> Token {
+        "abc.abc.abc".to_string().try_into().unwrap()
+    }
+
+    fn token_srv_for_session() -> TokenServiceMock {
+        let mut token_srv = TokenServiceMock::default();
+        token_srv.claims_fn = Some(|_: &TokenServiceMock, token: Token| {
+            Ok(Claims {
+                token,
+                payload: Payload::new(TokenKind::Session, Duration::from_secs(60)).with_subject("123"),
+            })
+        });
+
+        token_srv
+    }
+
+    /// A minimal in-memory backing store for `SecretRepositoryMock`, since `consume_recovery_code`
+    /// and `generate_recovery_codes` round-trip a secret's data through `find_by_owner_and_kind`,
+    /// `create`, and `save` within a single test rather than just returning a fixed value.
+    fn secret_repo_with_store() -> SecretRepositoryMock {
+        let store: Arc<Mutex<Option<Vec<u8>>>> = Arc::new(Mutex::new(None));
+        let mut secret_repo = SecretRepositoryMock::default();
+
+        let find_store = store.clone();
+        secret_repo.fn_find_by_owner_and_kind = Some(move |_: &SecretRepositoryMock, user_id, kind| {
+            find_store
+                .lock()
+                .unwrap()
+                .clone()
+                .map(|data| Secret::new(user_id, kind, data))
+                .ok_or(RepoError::NotFound)
+        });
+
+        let create_store = store.clone();
+        secret_repo.fn_create = Some(move |_: &SecretRepositoryMock, secret: &mut Secret| {
+            *create_store.lock().unwrap() = Some(secret.data().to_vec());
+            Ok(())
+        });
+
+        let save_store = store.clone();
+        secret_repo.fn_save = Some(move |_: &SecretRepositoryMock, secret: &Secret| {
+            *save_store.lock().unwrap() = Some(secret.data().to_vec());
+            Ok(())
+        });
+
+        secret_repo
+    }
+
+    #[tokio::test]
+    async fn confirm_totp_with_correct_code_should_generate_recovery_codes() {
+        let mut app = new_user_application();
+        app.token_srv = Arc::new(token_srv_for_session());
+        app.secret_repo = Arc::new(secret_repo_with_store());
+
+        app.enroll_totp(session_token()).await.unwrap();
+
+        let pending: PendingTotp = app.cache.find(&pending_totp_key(123)).await.unwrap();
+        let code = crypto::generate_totp(&pending.secret).unwrap().generate();
+
+        let recovery_codes = app.confirm_totp(session_token(), &code).await.unwrap();
+        assert_eq!(recovery_codes.len(), RECOVERY_CODE_COUNT);
+    }
+
+    #[tokio::test]
+    async fn confirm_totp_with_wrong_code_should_fail() {
+        let mut app = new_user_application();
+        app.token_srv = Arc::new(token_srv_for_session());
+
+        app.enroll_totp(session_token()).await.unwrap();
+
+        let result = app.confirm_totp(session_token(), "000000").await;
+        assert!(matches!(result, Err(Error::WrongCredentials)), "{:?}", result);
+    }
+
+    #[tokio::test]
+    async fn consume_recovery_code_should_match_and_remove_a_used_code() {
+        let mut app = new_user_application();
+        app.secret_repo = Arc::new(secret_repo_with_store());
+
+        let recovery_codes = app.generate_recovery_codes(999).await.unwrap();
+        let used_code = &recovery_codes[0];
+
+        let consumed = app.consume_recovery_code(999, used_code).await.unwrap();
+        assert!(consumed);
+
+        let replayed = app.consume_recovery_code(999, used_code).await.unwrap();
+        assert!(!replayed);
+    }
+
+    #[tokio::test]
+    async fn consume_recovery_code_with_unknown_code_should_report_unmatched() {
+        let mut app = new_user_application();
+        app.secret_repo = Arc::new(secret_repo_with_store());
+        app.generate_recovery_codes(999).await.unwrap();
+
+        let consumed = app.consume_recovery_code(999, "not-a-real-code&").await.unwrap();
+        assert!(!consumed);
+    }
+}
@@ -0,0 +1,170 @@
+use super::{EventBus, MailService, PushService, UserApplication, UserRepository};
+use crate::cache::Cache;
+use crate::mfa::domain::{Assertion, AssertionChallenge, Attestation, MfaMethod, RegistrationChallenge};
+use crate::mfa::service::MfaService;
+use crate::secret::application::SecretRepository;
+use crate::token::domain::Token;
+use crate::token::service::TokenService;
+use crate::user::error::{Error, Result};
+use std::time::Duration;
+
+/// How long a successful `finish_webauthn_assertion` is remembered for, under
+/// `webauthn_login_key`: long enough for the login call that triggered the ceremony to retry
+/// and pick it up, short enough that a stale proof can't be reused for an unrelated login later.
+const WEBAUTHN_LOGIN_PROOF_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Marks that `user_id` just completed a WebAuthn assertion, so a pending `login` call can treat
+/// it as the alternative to a TOTP code. Session's own `login_unthrottled` consumes this entry
+/// directly off the `Cache` rather than calling back into `UserApplication`, the same way it
+/// already talks straight to `SecretRepository` for the TOTP branch instead of routing through
+/// here.
+pub(crate) fn webauthn_login_key(user_id: i32) -> String {
+    format!("webauthn_login::{}", user_id)
+}
+
+impl<U, S, T, F, M, B, C, P> UserApplication<U, S, T, F, M, B, C, P>
+where
+    U: UserRepository,
+    S: SecretRepository,
+    T: TokenService,
+    F: MfaService,
+    M: MailService,
+    B: EventBus,
+    C: Cache,
+    P: PushService,
+{
+    /// Starts a WebAuthn registration ceremony for the user behind `session`, returning the
+    /// challenge and credential-creation options the client's authenticator must answer.
+    #[instrument(skip(self, session))]
+    pub async fn begin_webauthn_registration(&self, session: Token) -> Result<RegistrationChallenge> {
+        let claims = self.token_srv.claims(session).await?;
+        if !claims.payload().kind().is_session() {
+            return Err(Error::WrongToken);
+        }
+
+        let user_id: i32 = claims.payload().subject().parse().map_err(|_| Error::WrongToken)?;
+        let user = self.user_repo.find(user_id).await?;
+        self.multi_factor_srv
+            .begin_registration(&user)
+            .await
+            .map_err(Error::from)
+    }
+
+    /// Completes a WebAuthn registration ceremony for the user behind `session`, persisting the
+    /// resulting public-key credential as the user's `MfaMethod::Webauthn` secret.
+    #[instrument(skip(self, session, attestation))]
+    pub async fn finish_webauthn_registration(&self, session: Token, attestation: Attestation) -> Result<()> {
+        let claims = self.token_srv.claims(session).await?;
+        if !claims.payload().kind().is_session() {
+            return Err(Error::WrongToken);
+        }
+
+        let user_id: i32 = claims.payload().subject().parse().map_err(|_| Error::WrongToken)?;
+        let mut user = self.user_repo.find(user_id).await?;
+
+        self.multi_factor_srv
+            .finish_registration(&user, attestation)
+            .await
+            .map_err(Error::from)?;
+
+        user.preferences.multi_factor = Some(MfaMethod::Webauthn);
+        self.user_repo.save(&user).await.map_err(Into::into)
+    }
+
+    /// Starts a WebAuthn assertion ceremony, handed to the client during `login` as an
+    /// alternative to the TOTP step.
+    #[instrument(skip(self))]
+    pub async fn begin_webauthn_assertion(&self, user_id: i32) -> Result<AssertionChallenge> {
+        let user = self.user_repo.find(user_id).await?;
+        self.multi_factor_srv
+            .begin_assertion(&user)
+            .await
+            .map_err(Error::from)
+    }
+
+    /// Verifies the signature over `authenticatorData || SHA256(clientDataJSON)` against the
+    /// stored public key and enforces a monotonically increasing signature counter, persisting
+    /// the new counter on success so a cloned authenticator is detected on its next use.
+    #[instrument(skip(self, assertion))]
+    pub async fn finish_webauthn_assertion(&self, user_id: i32, assertion: Assertion) -> Result<()> {
+        let user = self.user_repo.find(user_id).await?;
+        self.multi_factor_srv
+            .finish_assertion(&user, assertion)
+            .await
+            .map_err(Error::from)?;
+
+        self.cache
+            .save(&webauthn_login_key(user_id), &true, WEBAUTHN_LOGIN_PROOF_TIMEOUT)
+            .await
+            .map_err(Into::into)
+    }
+}
+
+// `finish_webauthn_registration`/`finish_webauthn_assertion` are not covered here: both take an
+// `Attestation`/`Assertion` by value, and `crate::mfa::domain` (where those are defined) is not
+// present in this tree, so no test in this file can construct one. The two ceremony-start
+// entrypoints below don't have that problem, since their failure paths return before any
+// `mfa::domain` value needs to exist.
+#[cfg(test)]
+mod test {
+    use crate::token::domain::{Claims, Payload, Token, TokenKind};
+    use crate::token::service::test::TokenServiceMock;
+    use crate::user::application::test::{new_user_application, UserRepositoryMock};
+    use crate::user::error::Error;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn session_token() -> Token {
+        "abc.abc.abc".to_string().try_into().unwrap()
+    }
+
+    fn refresh_token() -> Token {
+        "abc.abc.abc".to_string().try_into().unwrap()
+    }
+
+    fn token_srv_with_kind(kind: TokenKind) -> TokenServiceMock {
+        let mut token_srv = TokenServiceMock::default();
+        token_srv.claims_fn = Some(move |_: &TokenServiceMock, token: Token| {
+            Ok(Claims {
+                token,
+                payload: Payload::new(kind, Duration::from_secs(60)).with_subject("123"),
+            })
+        });
+
+        token_srv
+    }
+
+    #[tokio::test]
+    async fn begin_webauthn_registration_with_a_non_session_token_should_fail() {
+        let mut app = new_user_application();
+        app.token_srv = Arc::new(token_srv_with_kind(TokenKind::Refresh));
+
+        let result = app.begin_webauthn_registration(refresh_token()).await;
+        assert!(matches!(result, Err(Error::WrongToken)), "{:?}", result);
+    }
+
+    #[tokio::test]
+    async fn begin_webauthn_registration_with_unknown_user_should_fail() {
+        let mut app = new_user_application();
+        app.token_srv = Arc::new(token_srv_with_kind(TokenKind::Session));
+
+        let mut user_repo = UserRepositoryMock::default();
+        user_repo.find_fn = Some(|_: &UserRepositoryMock, _: i32| Err(Error::NotFound));
+        app.user_repo = Arc::new(user_repo);
+
+        let result = app.begin_webauthn_registration(session_token()).await;
+        assert!(matches!(result, Err(Error::NotFound)), "{:?}", result);
+    }
+
+    #[tokio::test]
+    async fn begin_webauthn_assertion_with_unknown_user_should_fail() {
+        let mut app = new_user_application();
+
+        let mut user_repo = UserRepositoryMock::default();
+        user_repo.find_fn = Some(|_: &UserRepositoryMock, _: i32| Err(Error::NotFound));
+        app.user_repo = Arc::new(user_repo);
+
+        let result = app.begin_webauthn_assertion(999).await;
+        assert!(matches!(result, Err(Error::NotFound)), "{:?}", result);
+    }
+}